@@ -0,0 +1,74 @@
+//! Reading text placement from TOML files. Enabled with the `config` feature.
+
+use std::path::{Path, PathBuf};
+
+use image::{DynamicImage, Rgba};
+use rusttype::Scale;
+use serde::Deserialize;
+
+use crate::{
+    load_font, text_on_image, FontBundle, TextJustify, TextMeasurement, TextOnImageError,
+    VerticalAnchor, WrapBehavior,
+};
+
+/// Every parameter of [`text_on_image`], as read from a TOML file.
+///
+/// ```toml
+/// text = "Hello"
+/// font_path = "fonts/MyFont.ttf"
+/// scale = 40.0
+/// color = [0, 255, 0, 255]
+/// x = 400
+/// y = 800
+/// wrap_width = 250
+/// justify = "Center"
+/// anchor = "Top"
+/// ```
+///
+/// `wrap_width`, `justify` and `anchor` are optional. A relative `font_path` is
+/// resolved against the directory containing the config file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TextConfig {
+    pub text: String,
+    pub font_path: PathBuf,
+    pub scale: f32,
+    pub color: [u8; 4],
+    pub x: i32,
+    pub y: i32,
+    #[serde(default)]
+    pub wrap_width: Option<u32>,
+    #[serde(default)]
+    pub justify: TextJustify,
+    #[serde(default)]
+    pub anchor: VerticalAnchor,
+}
+
+/// Reads a [`TextConfig`] from a TOML file and draws it on the image.
+pub fn text_on_image_from_config_file<P: AsRef<Path>>(
+    image: &mut DynamicImage,
+    config_path: P,
+) -> Result<TextMeasurement, TextOnImageError> {
+    let config_path = config_path.as_ref();
+    let contents = std::fs::read_to_string(config_path)?;
+    let config: TextConfig = toml::from_str(&contents).map_err(TextOnImageError::ConfigError)?;
+    let font_path = match config_path.parent() {
+        Some(parent) if config.font_path.is_relative() => parent.join(&config.font_path),
+        _ => config.font_path.clone(),
+    };
+    let font = load_font(font_path)?;
+    let font_bundle = FontBundle::new(&font, Scale::uniform(config.scale), Rgba(config.color));
+    let wrap_behavior = match config.wrap_width {
+        Some(max_width) => WrapBehavior::Wrap(max_width),
+        None => WrapBehavior::NoWrap,
+    };
+    Ok(text_on_image(
+        image,
+        config.text,
+        &font_bundle,
+        config.x,
+        config.y,
+        config.justify,
+        config.anchor,
+        wrap_behavior,
+    ))
+}
@@ -0,0 +1,1169 @@
+//! Effects that render text to a scratch layer first and then composite it onto the image.
+
+use image::{DynamicImage, GenericImage, GenericImageView, GrayImage, Luma, Rgba};
+use imageproc::distance_transform::Norm;
+use imageproc::filter::{filter3x3, gaussian_blur_f32};
+use imageproc::geometric_transformations::{warp_into, warp_into_with, Interpolation, Projection};
+use imageproc::morphology::{dilate, erode};
+
+use crate::{
+    draw_shadow, get_text_height, measure_wrapped_text, text_on_image, FontBundle, ShadowConfig,
+    TextBoundingBox, TextJustify, TextMeasurement, VerticalAnchor, WrapBehavior,
+};
+
+/// How a text layer is combined with the image beneath it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Ordinary alpha-over compositing, the same as [`text_on_image`].
+    #[default]
+    Normal,
+    /// Multiplies the text color with the image, which always darkens.
+    Multiply,
+    /// Inverse of multiply, which always lightens.
+    Screen,
+    /// Multiplies dark parts of the image and screens light parts, keeping its contrast.
+    Overlay,
+    /// Like overlay, but chooses between multiply and screen based on the text color.
+    HardLight,
+}
+
+impl BlendMode {
+    /// Blends one channel of the text color onto one channel of the image, both from 0 to 1.
+    fn blend_channel(self, backdrop: f32, source: f32) -> f32 {
+        let multiply = |a: f32, b: f32| a * b;
+        let screen = |a: f32, b: f32| a + b - a * b;
+        match self {
+            BlendMode::Normal => source,
+            BlendMode::Multiply => multiply(backdrop, source),
+            BlendMode::Screen => screen(backdrop, source),
+            BlendMode::Overlay => BlendMode::HardLight.blend_channel(source, backdrop),
+            BlendMode::HardLight => {
+                if source <= 0.5 {
+                    multiply(backdrop, 2. * source)
+                } else {
+                    screen(backdrop, 2. * source - 1.)
+                }
+            }
+        }
+    }
+}
+
+/// Settings for a shadow cast inside the glyphs, making text look recessed or engraved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InnerShadowConfig {
+    /// How far the light is from the text horizontally. Positive values shade the left edges.
+    pub offset_x: i32,
+    /// How far the light is from the text vertically. Positive values shade the top edges.
+    pub offset_y: i32,
+    pub color: Rgba<u8>,
+    /// Gaussian blur sigma for softening the shadow. Zero keeps a hard edge.
+    pub blur: f32,
+}
+
+/// A drop shadow under the text and an inner shadow inside it, either of which may be left out.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CombinedShadowConfig {
+    pub drop_shadow: Option<ShadowConfig>,
+    pub inner_shadow: Option<InnerShadowConfig>,
+}
+
+/// A watermark repeated in a grid over a whole image by [`text_on_image_watermark_grid`].
+#[derive(Clone)]
+pub struct WatermarkGridConfig<'a> {
+    pub text: String,
+    pub font_bundle: FontBundle<'a>,
+    /// The gap between the end of one copy and the start of the next along a row.
+    pub spacing_x: u32,
+    /// The gap between rows.
+    pub spacing_y: u32,
+    /// How far the grid is turned counterclockwise, in degrees, so 45 runs the text up to the
+    /// right.
+    pub angle_deg: f32,
+    /// Multiplies the font bundle color's alpha, from 0 for invisible to 255 for unchanged.
+    pub opacity: u8,
+}
+
+/// A shadow grown outward from the glyphs before it is moved and blurred, like the spread of a
+/// CSS `box-shadow`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpreadShadowConfig {
+    pub offset_x: i32,
+    pub offset_y: i32,
+    /// How many pixels the shadow grows past the glyph edges, up to 255.
+    pub spread_px: u32,
+    /// Gaussian blur sigma for softening the shadow. Zero keeps a hard edge.
+    pub blur_radius: f32,
+    pub color: Rgba<u8>,
+}
+
+/// Settings for a soft colored glow around text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NeonConfig {
+    pub glow_color: Rgba<u8>,
+    /// Gaussian blur sigma for spreading the glow. Larger values reach further from the text.
+    pub glow_radius: f32,
+    /// Multiplies the glow's opacity. Values above 1.0 make it brighter and less faded.
+    pub glow_intensity: f32,
+}
+
+/// Which way a [`GradientShadowConfig`] shadow stretches away from the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowDirection {
+    Down,
+    Up,
+    Left,
+    Right,
+}
+
+/// Settings for a shadow that fades out as it stretches away from the text, like a shadow cast
+/// on a surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradientShadowConfig {
+    pub direction: ShadowDirection,
+    /// How many pixels the shadow reaches before it is fully transparent.
+    pub length: u32,
+    /// The shadow's color right next to the text.
+    pub color: Rgba<u8>,
+}
+
+/// The shape [`text_on_image_with_warp`] bends text into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WarpPath {
+    /// Text running clockwise around a circle, with the bottom of the text on the circle and the
+    /// letters pointing away from the center. `start_angle` is where the text begins, in degrees
+    /// clockwise from the right, so -90 starts at the top of the circle.
+    Arc {
+        center_x: i32,
+        center_y: i32,
+        radius: f32,
+        start_angle: f32,
+    },
+    /// Text stretched so its top left, top right, bottom right and bottom left corners land on
+    /// these image coordinates, in that order.
+    Perspective { corners: [(f32, f32); 4] },
+}
+
+/// Settings for a raised bevel, lit from the top left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BevelConfig {
+    /// Color of the edges facing the top left.
+    pub light_color: Rgba<u8>,
+    /// Color of the edges facing the bottom right.
+    pub shadow_color: Rgba<u8>,
+    /// How many pixels in from the outside of the glyphs the beveled edges reach, up to 255.
+    pub depth: u32,
+}
+
+/// Settings for the blurred backdrop [`text_on_image_frosted_glass`] draws behind text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrostedGlassConfig {
+    /// Gaussian blur sigma for the backdrop. Larger values hide more of the image beneath.
+    pub blur_radius: f32,
+    /// A color drawn over the blurred backdrop, with its own alpha as the strength.
+    pub tint: Option<Rgba<u8>>,
+    /// How much the finished backdrop replaces the image, from 0 for not at all to 255 for fully.
+    pub opacity: u8,
+}
+
+/// Renders text as a grayscale coverage mask the size of `width` by `height`.
+///
+/// Each pixel is 255 where a glyph fully covers it and 0 where no glyph touches it. The color of
+/// `font_bundle` is ignored. Returns the mask and the area the text covers.
+pub fn render_text_to_mask<T: AsRef<str>>(
+    width: u32,
+    height: u32,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> (GrayImage, TextMeasurement) {
+    let mut mask_bundle = *font_bundle;
+    mask_bundle.set_color(Rgba([255, 255, 255, 255]));
+    let mut mask = DynamicImage::new_luma8(width, height);
+    let measurement = text_on_image(
+        &mut mask,
+        text,
+        &mask_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    (mask.into_luma8(), measurement)
+}
+
+/// Draws text on an image, combining it with the pixels beneath using `blend`.
+///
+/// The text is rendered to a mask first, so anti-aliased edges blend smoothly. Useful for
+/// photography-style overlays, where `Multiply` lets darker text pick up the image's texture
+/// and `Screen` does the same for lighter text.
+pub fn text_on_image_with_blend<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    blend: BlendMode,
+) -> TextMeasurement {
+    let (mask, measurement) = render_text_to_mask(
+        image.width(),
+        image.height(),
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    composite_mask(image, &mask, font_bundle.color(), blend);
+    measurement
+}
+
+/// Draws text on an image with a shadow inside the glyphs along the edges facing the light.
+///
+/// The shadow is the part of the text not covered by a copy moved by the shadow's offset, blurred
+/// and then clipped to the glyphs. Since it is drawn over the text, inner shadows only look
+/// correct on text colors lighter than the shadow color, so avoid black text.
+pub fn text_on_image_with_inner_shadow<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    inner_shadow: InnerShadowConfig,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let (width, height) = (image.width(), image.height());
+    let (text_mask, measurement) = render_text_to_mask(
+        width,
+        height,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let (offset_mask, _) = render_text_to_mask(
+        width,
+        height,
+        text,
+        font_bundle,
+        pixels_from_left + inner_shadow.offset_x,
+        pixels_from_top + inner_shadow.offset_y,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let mut shadow_mask = GrayImage::from_fn(width, height, |x, y| {
+        Luma([text_mask.get_pixel(x, y)[0].saturating_sub(offset_mask.get_pixel(x, y)[0])])
+    });
+    if inner_shadow.blur > 0. {
+        shadow_mask = gaussian_blur_f32(&shadow_mask, inner_shadow.blur);
+    }
+    let shadow_mask = multiply_masks(&shadow_mask, &text_mask);
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    composite_mask(image, &shadow_mask, inner_shadow.color, BlendMode::Normal);
+    measurement
+}
+
+/// Draws text lifted off the image by a drop shadow and given depth by an inner shadow.
+///
+/// The drop shadow is drawn first, then the text, then the inner shadow clipped to the glyphs,
+/// as in [`text_on_image_with_inner_shadow`]. Without either shadow this draws plain text.
+pub fn text_on_image_with_combined_shadow<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    config: CombinedShadowConfig,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    if let Some(drop_shadow) = config.drop_shadow {
+        draw_shadow(
+            image,
+            text,
+            font_bundle,
+            pixels_from_left,
+            pixels_from_top,
+            horizontal_justify,
+            vertical_anchor,
+            wrap_behavior,
+            drop_shadow,
+        );
+    }
+    match config.inner_shadow {
+        Some(inner_shadow) => text_on_image_with_inner_shadow(
+            image,
+            text,
+            font_bundle,
+            pixels_from_left,
+            pixels_from_top,
+            horizontal_justify,
+            vertical_anchor,
+            wrap_behavior,
+            inner_shadow,
+        ),
+        None => text_on_image(
+            image,
+            text,
+            font_bundle,
+            pixels_from_left,
+            pixels_from_top,
+            horizontal_justify,
+            vertical_anchor,
+            wrap_behavior,
+        ),
+    }
+}
+
+/// Draws text on an image and then embosses it, so it looks raised from the surface.
+///
+/// `emboss_direction` is the angle the light comes from in degrees, counterclockwise from the
+/// right, so 90 lights the text from above. `emboss_depth` is how many levels of brightness the
+/// edges facing the light gain, and the edges facing away lose. Flat areas are unchanged, as if
+/// the relief were added to the image around a 50% gray.
+pub fn text_on_image_with_emboss<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    emboss_direction: f32,
+    emboss_depth: f32,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let (heightfield, measurement) = render_text_to_mask(
+        image.width(),
+        image.height(),
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let relief: image::ImageBuffer<Luma<f32>, Vec<f32>> =
+        filter3x3(&heightfield, &emboss_kernel(emboss_direction));
+    for (x, y, height_change) in relief.enumerate_pixels() {
+        // A full step from empty to covered across the kernel adds up to about 3 * 255.
+        let delta = height_change[0] / (3. * 255.) * emboss_depth;
+        if delta == 0. {
+            continue;
+        }
+        let mut pixel = image.get_pixel(x, y);
+        for channel in pixel.0.iter_mut().take(3) {
+            *channel = (*channel as f32 + delta).round().clamp(0., 255.) as u8;
+        }
+        image.put_pixel(x, y, pixel);
+    }
+    measurement
+}
+
+/// Draws text on an image over a blurred glow, like a neon sign.
+///
+/// The glow is a blurred copy of the text in `neon.glow_color`, and the sharp text is drawn on
+/// top of it in the font bundle's color.
+pub fn text_on_image_with_neon_glow<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    neon: NeonConfig,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let (mut glow_mask, _) = render_text_to_mask(
+        image.width(),
+        image.height(),
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    if neon.glow_radius > 0. {
+        glow_mask = gaussian_blur_f32(&glow_mask, neon.glow_radius);
+    }
+    for coverage in glow_mask.pixels_mut() {
+        coverage[0] = (coverage[0] as f32 * neon.glow_intensity)
+            .round()
+            .clamp(0., 255.) as u8;
+    }
+    composite_mask(image, &glow_mask, neon.glow_color, BlendMode::Normal);
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text bent along an arc or into a perspective quadrilateral.
+///
+/// The text is laid out flat first and then resampled onto the image, so it is only as accurate
+/// as the pixel grid allows: tight arcs and steep perspectives blur or alias small text, and
+/// larger font scales hold up better. Returns the pixels the warped text paints.
+///
+/// Panics if the perspective corners do not form a valid quadrilateral.
+pub fn text_on_image_with_warp<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    warp: WarpPath,
+    horizontal_justify: TextJustify,
+    wrap_behavior: WrapBehavior,
+) -> TextBoundingBox {
+    let text = text.as_ref();
+    let block = measure_wrapped_text(
+        text,
+        font_bundle,
+        0,
+        0,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        wrap_behavior,
+    );
+    let justify_x = match horizontal_justify {
+        TextJustify::Center => block.width as i32 / 2,
+        TextJustify::Right => block.width as i32,
+        _ => 0,
+    };
+    let (flat_mask, _) = render_text_to_mask(
+        block.width.max(1),
+        block.height.max(1),
+        text,
+        font_bundle,
+        justify_x,
+        0,
+        horizontal_justify,
+        VerticalAnchor::Top,
+        wrap_behavior,
+    );
+    let (flat_width, flat_height) = (block.width as f32, block.height as f32);
+    let mut warped_mask = GrayImage::new(image.width(), image.height());
+    match warp {
+        WarpPath::Arc {
+            center_x,
+            center_y,
+            radius,
+            start_angle,
+        } => {
+            let arc_to_flat = |x: f32, y: f32| {
+                let (dx, dy) = (x - center_x as f32, y - center_y as f32);
+                let angle = (dy.atan2(dx).to_degrees() - start_angle).rem_euclid(360.);
+                let flat_x = angle.to_radians() * radius;
+                let flat_y = flat_height - ((dx * dx + dy * dy).sqrt() - radius);
+                (flat_x, flat_y)
+            };
+            warp_into_with(
+                &flat_mask,
+                arc_to_flat,
+                Interpolation::Bilinear,
+                Luma([0]),
+                &mut warped_mask,
+            );
+        }
+        WarpPath::Perspective { corners } => {
+            let flat_corners = [
+                (0., 0.),
+                (flat_width, 0.),
+                (flat_width, flat_height),
+                (0., flat_height),
+            ];
+            let projection = Projection::from_control_points(flat_corners, corners)
+                .expect("text_on_image: Perspective corners must form a valid quadrilateral!");
+            warp_into(
+                &flat_mask,
+                &projection,
+                Interpolation::Bilinear,
+                Luma([0]),
+                &mut warped_mask,
+            );
+        }
+    }
+    composite_mask(image, &warped_mask, font_bundle.color(), BlendMode::Normal);
+    mask_bounding_box(&warped_mask)
+}
+
+/// Helper function to find the pixels a mask covers, or an empty box at the origin if none.
+fn mask_bounding_box(mask: &GrayImage) -> TextBoundingBox {
+    let mut bounding_box: Option<TextBoundingBox> = None;
+    for (x, y, coverage) in mask.enumerate_pixels() {
+        if coverage[0] == 0 {
+            continue;
+        }
+        let (x, y) = (x as i32, y as i32);
+        bounding_box = Some(match bounding_box {
+            None => TextBoundingBox {
+                left: x,
+                top: y,
+                right: x + 1,
+                bottom: y + 1,
+            },
+            Some(b) => TextBoundingBox {
+                left: b.left.min(x),
+                top: b.top.min(y),
+                right: b.right.max(x + 1),
+                bottom: b.bottom.max(y + 1),
+            },
+        });
+    }
+    bounding_box.unwrap_or_default()
+}
+
+/// Draws text on an image over a shadow that fades from `shadow.color` to transparent.
+///
+/// The shadow is made of copies of the text at every offset up to `shadow.length`, where the
+/// copy `i` pixels away has `1.0 - i / length` of the shadow color's alpha.
+pub fn text_on_image_with_text_shadow_gradient<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    shadow: GradientShadowConfig,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let (width, height) = (image.width(), image.height());
+    let (text_mask, _) = render_text_to_mask(
+        width,
+        height,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let (step_x, step_y) = match shadow.direction {
+        ShadowDirection::Down => (0, 1),
+        ShadowDirection::Up => (0, -1),
+        ShadowDirection::Left => (-1, 0),
+        ShadowDirection::Right => (1, 0),
+    };
+    let mut shadow_mask = GrayImage::new(width, height);
+    for offset in 1..shadow.length as i32 {
+        let strength = 1. - offset as f32 / shadow.length as f32;
+        for (x, y, coverage) in text_mask.enumerate_pixels() {
+            if coverage[0] == 0 {
+                continue;
+            }
+            let (shadow_x, shadow_y) = (x as i32 + offset * step_x, y as i32 + offset * step_y);
+            if (0..width as i32).contains(&shadow_x) && (0..height as i32).contains(&shadow_y) {
+                let faded = (coverage[0] as f32 * strength).round() as u8;
+                let pixel = shadow_mask.get_pixel_mut(shadow_x as u32, shadow_y as u32);
+                pixel[0] = pixel[0].max(faded);
+            }
+        }
+    }
+    composite_mask(image, &shadow_mask, shadow.color, BlendMode::Normal);
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws only a ring around the outside of each glyph, leaving the glyphs themselves hollow.
+///
+/// The ring is `stroke_width` pixels wide, up to 255, and is drawn in the font bundle's color.
+/// Its outer edge is not anti-aliased.
+pub fn text_on_image_stroke_text<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    stroke_width: u32,
+) -> TextMeasurement {
+    let (text_mask, measurement) = render_text_to_mask(
+        image.width(),
+        image.height(),
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let mut ring = dilate(&text_mask, Norm::LInf, stroke_width.min(255) as u8);
+    for (ring_pixel, text_pixel) in ring.pixels_mut().zip(text_mask.pixels()) {
+        ring_pixel[0] = ring_pixel[0].saturating_sub(text_pixel[0]);
+    }
+    composite_mask(image, &ring, font_bundle.color(), BlendMode::Normal);
+    measurement
+}
+
+/// Draws text with beveled edges so it looks like a raised button.
+///
+/// The outer `bevel.depth` pixels of each glyph are drawn in `bevel.light_color` where they face
+/// the top left and in `bevel.shadow_color` where they face the bottom right, and the rest of the
+/// glyph is drawn in the font bundle's color.
+pub fn text_on_image_bevel<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    bevel: BevelConfig,
+) -> TextMeasurement {
+    let (text_mask, measurement) = render_text_to_mask(
+        image.width(),
+        image.height(),
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let depth = bevel.depth.min(255);
+    let interior = erode(&text_mask, Norm::LInf, depth as u8);
+    let coverage_at = |x: i64, y: i64| {
+        if x < 0 || y < 0 || x >= text_mask.width() as i64 || y >= text_mask.height() as i64 {
+            0
+        } else {
+            text_mask.get_pixel(x as u32, y as u32)[0]
+        }
+    };
+    let mut light_mask = GrayImage::new(text_mask.width(), text_mask.height());
+    let mut shadow_mask = GrayImage::new(text_mask.width(), text_mask.height());
+    let mut interior_mask = GrayImage::new(text_mask.width(), text_mask.height());
+    for (x, y, coverage) in text_mask.enumerate_pixels() {
+        if coverage[0] == 0 {
+            continue;
+        }
+        if interior.get_pixel(x, y)[0] != 0 {
+            interior_mask.put_pixel(x, y, *coverage);
+            continue;
+        }
+        // An edge faces whichever diagonal leaves the glyph sooner.
+        let (x, y, reach) = (x as i64, y as i64, depth.max(1) as i64);
+        let toward_light = coverage_at(x - reach, y - reach);
+        let toward_shadow = coverage_at(x + reach, y + reach);
+        if toward_light <= toward_shadow {
+            light_mask.put_pixel(x as u32, y as u32, *coverage);
+        } else {
+            shadow_mask.put_pixel(x as u32, y as u32, *coverage);
+        }
+    }
+    composite_mask(
+        image,
+        &interior_mask,
+        font_bundle.color(),
+        BlendMode::Normal,
+    );
+    composite_mask(image, &light_mask, bevel.light_color, BlendMode::Normal);
+    composite_mask(image, &shadow_mask, bevel.shadow_color, BlendMode::Normal);
+    measurement
+}
+
+/// Draws text over a frosted glass backdrop made by blurring the image behind it.
+///
+/// The backdrop covers the measured area of the text, clipped to the image. Only pixels inside
+/// that area are blurred, so the backdrop does not pick up color from outside it.
+pub fn text_on_image_frosted_glass<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    glass: FrostedGlassConfig,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let area = measure_wrapped_text(
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let left = area.left.clamp(0, image.width() as i32) as u32;
+    let top = area.top.clamp(0, image.height() as i32) as u32;
+    let right = (area.left + area.width as i32).clamp(0, image.width() as i32) as u32;
+    let bottom = (area.top + area.height as i32).clamp(0, image.height() as i32) as u32;
+    if right > left && bottom > top {
+        let mut backdrop = image.crop_imm(left, top, right - left, bottom - top);
+        if glass.blur_radius > 0. {
+            backdrop = backdrop.blur(glass.blur_radius);
+        }
+        let opacity = glass.opacity as f32 / 255.;
+        for (x, y, frosted) in backdrop.pixels() {
+            let frosted = match glass.tint {
+                Some(tint) => blend_pixel(frosted, tint, tint[3] as f32 / 255., BlendMode::Normal),
+                None => frosted,
+            };
+            let (x, y) = (left + x, top + y);
+            let mut pixel = image.get_pixel(x, y);
+            for (channel, frosted_channel) in pixel.0.iter_mut().zip(frosted.0) {
+                *channel = (*channel as f32 * (1. - opacity) + frosted_channel as f32 * opacity)
+                    .round() as u8;
+            }
+            image.put_pixel(x, y, pixel);
+        }
+    }
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text over a shadow made from the image itself, a blurred and darkened copy of what lies
+/// under the shadow, so it picks up the local colors like a real shadow.
+///
+/// The shadow falls `shadow_offset` pixels from the text and its edges are softened with the
+/// same `blur_radius` as the image beneath it. `darken_factor` runs from 0, which keeps the
+/// image's brightness, to 1, which makes the shadow black, and is clamped to that range.
+pub fn text_on_image_image_derived_shadow<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    blur_radius: f32,
+    darken_factor: f32,
+    shadow_offset: (i32, i32),
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let shadow_left = pixels_from_left + shadow_offset.0;
+    let shadow_top = pixels_from_top + shadow_offset.1;
+    let (mut shadow_mask, area) = render_text_to_mask(
+        image.width(),
+        image.height(),
+        text,
+        font_bundle,
+        shadow_left,
+        shadow_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    if blur_radius > 0. {
+        shadow_mask = gaussian_blur_f32(&shadow_mask, blur_radius);
+    }
+    // A blurred edge fades out within about three sigma of the text.
+    let reach = (blur_radius.max(0.) * 3.).ceil() as i32;
+    let left = (area.left - reach).clamp(0, image.width() as i32) as u32;
+    let top = (area.top - reach).clamp(0, image.height() as i32) as u32;
+    let right = (area.left + area.width as i32 + reach).clamp(0, image.width() as i32) as u32;
+    let bottom = (area.top + area.height as i32 + reach).clamp(0, image.height() as i32) as u32;
+    if right > left && bottom > top {
+        let mut backdrop = image.crop_imm(left, top, right - left, bottom - top);
+        if blur_radius > 0. {
+            backdrop = backdrop.blur(blur_radius);
+        }
+        let brightness = 1. - darken_factor.clamp(0., 1.);
+        for (x, y, mut shade) in backdrop.pixels() {
+            let (x, y) = (left + x, top + y);
+            let coverage = shadow_mask.get_pixel(x, y)[0];
+            if coverage == 0 {
+                continue;
+            }
+            for channel in &mut shade.0[..3] {
+                *channel = (*channel as f32 * brightness).round() as u8;
+            }
+            let source_alpha = coverage as f32 / 255. * shade[3] as f32 / 255.;
+            let pixel = image.get_pixel(x, y);
+            image.put_pixel(
+                x,
+                y,
+                blend_pixel(pixel, shade, source_alpha, BlendMode::Normal),
+            );
+        }
+    }
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text over a shadow that is grown by `config.spread_px` in every direction, then moved
+/// and blurred.
+///
+/// A grown shadow's edge is not anti-aliased, so give it some blur to smooth it out.
+pub fn text_on_image_with_spread_shadow<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    config: SpreadShadowConfig,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let (mut shadow_mask, _) = render_text_to_mask(
+        image.width(),
+        image.height(),
+        text,
+        font_bundle,
+        pixels_from_left + config.offset_x,
+        pixels_from_top + config.offset_y,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    if config.spread_px > 0 {
+        shadow_mask = dilate(&shadow_mask, Norm::LInf, config.spread_px.min(255) as u8);
+    }
+    if config.blur_radius > 0. {
+        shadow_mask = gaussian_blur_f32(&shadow_mask, config.blur_radius);
+    }
+    composite_mask(image, &shadow_mask, config.color, BlendMode::Normal);
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text as unfilled outlines, like a stencil, without needing an outline font.
+///
+/// The same as [`text_on_image_stroke_text`] with a stroke `outline_width` pixels wide.
+pub fn text_on_image_outline_only<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    outline_width: u32,
+) -> TextMeasurement {
+    text_on_image_stroke_text(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+        outline_width,
+    )
+}
+
+/// Draws text leaning to the right like italics, for fonts without an italic style.
+///
+/// Each line is sheared about its baseline, so a point `h` pixels above the baseline moves
+/// `slant_factor * h` pixels right. A `slant_factor` of 0.3 leans about 17 degrees, and negative
+/// values lean left. Wrap widths shrink by the extra width the shear adds to a line, so wrapped
+/// lines still fit. Returns the area of the text before it is sheared.
+pub fn text_on_image_with_slant<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    slant_factor: f32,
+) -> TextMeasurement {
+    let line_height = get_text_height(font_bundle);
+    let widening = (slant_factor.abs() * line_height as f32).ceil() as u32;
+    let wrap_behavior = match wrap_behavior {
+        WrapBehavior::Wrap(max_width) => WrapBehavior::Wrap(max_width.saturating_sub(widening)),
+        WrapBehavior::WrapOptimal(max_width) => {
+            WrapBehavior::WrapOptimal(max_width.saturating_sub(widening))
+        }
+        WrapBehavior::WrapStrictNoHyphen(max_width) => {
+            WrapBehavior::WrapStrictNoHyphen(max_width.saturating_sub(widening))
+        }
+        other => other,
+    };
+    let (text_mask, measurement) = render_text_to_mask(
+        image.width(),
+        image.height(),
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let ascent = font_bundle.font.v_metrics(font_bundle.scale).ascent;
+    let last_line = measurement.line_count.max(1) as i32 - 1;
+    let baseline_of_row = |y: f32| {
+        let line =
+            ((y as i32 - measurement.top).div_euclid(line_height.max(1))).clamp(0, last_line);
+        (measurement.top + line * line_height) as f32 + ascent
+    };
+    let mut slanted_mask = GrayImage::new(image.width(), image.height());
+    warp_into_with(
+        &text_mask,
+        |x, y| (x - slant_factor * (baseline_of_row(y) - y), y),
+        Interpolation::Bilinear,
+        Luma([0]),
+        &mut slanted_mask,
+    );
+    composite_mask(image, &slanted_mask, font_bundle.color(), BlendMode::Normal);
+    measurement
+}
+
+/// Makes a copy of `background` with `foreground` showing through the shape of the text, as if
+/// the text were a window into `foreground`.
+///
+/// The result is the size of `background`, and the two images are lined up by their top left
+/// corners. Where `foreground` is smaller, only `background` shows. Anti-aliased edges of the
+/// text mix the two images.
+pub fn text_on_image_clip_mask<T: AsRef<str>>(
+    foreground: &DynamicImage,
+    background: &DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> DynamicImage {
+    let (text_mask, _) = render_text_to_mask(
+        background.width(),
+        background.height(),
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let mut composited = background.clone();
+    for (x, y, coverage) in text_mask.enumerate_pixels() {
+        if coverage[0] == 0 || x >= foreground.width() || y >= foreground.height() {
+            continue;
+        }
+        let color = foreground.get_pixel(x, y);
+        let source_alpha = coverage[0] as f32 / 255. * color[3] as f32 / 255.;
+        let pixel = composited.get_pixel(x, y);
+        composited.put_pixel(
+            x,
+            y,
+            blend_pixel(pixel, color, source_alpha, BlendMode::Normal),
+        );
+    }
+    composited
+}
+
+/// Covers the whole image with copies of a watermark in a turned grid, so it cannot be cropped
+/// out.
+///
+/// Each copy sits in a cell as wide as the text plus `config.spacing_x` and as tall as the text
+/// plus `config.spacing_y`. The grid is centered on the image and every pixel is mapped back
+/// into the unturned grid, so no corner is left uncovered however it is turned.
+pub fn text_on_image_watermark_grid(image: &mut DynamicImage, config: &WatermarkGridConfig<'_>) {
+    let text_size = measure_wrapped_text(
+        &config.text,
+        &config.font_bundle,
+        0,
+        0,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    let cell_width = (text_size.width + config.spacing_x).max(1);
+    let cell_height = (text_size.height + config.spacing_y).max(1);
+    // Half the spacing on each side keeps glyphs that overhang their advance inside the cell.
+    let (cell_mask, _) = render_text_to_mask(
+        cell_width,
+        cell_height,
+        &config.text,
+        &config.font_bundle,
+        (config.spacing_x / 2) as i32,
+        (config.spacing_y / 2) as i32,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    let (sin, cos) = config.angle_deg.to_radians().sin_cos();
+    let center_x = image.width() as f32 / 2.;
+    let center_y = image.height() as f32 / 2.;
+    let mut grid_mask = GrayImage::new(image.width(), image.height());
+    warp_into_with(
+        &cell_mask,
+        |x, y| {
+            let (dx, dy) = (x - center_x, y - center_y);
+            let grid_x = center_x + dx * cos - dy * sin;
+            let grid_y = center_y + dx * sin + dy * cos;
+            (
+                grid_x.rem_euclid(cell_width as f32),
+                grid_y.rem_euclid(cell_height as f32),
+            )
+        },
+        Interpolation::Bilinear,
+        Luma([0]),
+        &mut grid_mask,
+    );
+    for coverage in grid_mask.pixels_mut() {
+        coverage[0] = (coverage[0] as u32 * config.opacity as u32 / 255) as u8;
+    }
+    composite_mask(
+        image,
+        &grid_mask,
+        config.font_bundle.color(),
+        BlendMode::Normal,
+    );
+}
+
+/// Helper function to build a 3x3 kernel that responds to slopes facing light from `direction`.
+fn emboss_kernel(direction: f32) -> [f32; 9] {
+    let (light_x, light_y) = (direction.to_radians().cos(), -direction.to_radians().sin());
+    let mut kernel = [0.; 9];
+    for (index, weight) in kernel.iter_mut().enumerate() {
+        let offset_x = (index % 3) as f32 - 1.;
+        let offset_y = (index / 3) as f32 - 1.;
+        // Height rising away from the light means the slope faces it.
+        *weight = -(offset_x * light_x + offset_y * light_y);
+    }
+    kernel
+}
+
+/// Helper function to multiply two masks of the same size together, keeping only what both cover.
+fn multiply_masks(first: &GrayImage, second: &GrayImage) -> GrayImage {
+    GrayImage::from_fn(first.width(), first.height(), |x, y| {
+        let product = first.get_pixel(x, y)[0] as u16 * second.get_pixel(x, y)[0] as u16;
+        Luma([(product / 255) as u8])
+    })
+}
+
+/// Helper function to composite `color` onto the image wherever `mask` covers it.
+///
+/// The mask must be the same size as the image.
+pub(crate) fn composite_mask(
+    image: &mut DynamicImage,
+    mask: &GrayImage,
+    color: Rgba<u8>,
+    blend: BlendMode,
+) {
+    for (x, y, coverage) in mask.enumerate_pixels() {
+        if coverage[0] == 0 {
+            continue;
+        }
+        let source_alpha = coverage[0] as f32 / 255. * color[3] as f32 / 255.;
+        let pixel = image.get_pixel(x, y);
+        image.put_pixel(x, y, blend_pixel(pixel, color, source_alpha, blend));
+    }
+}
+
+/// Helper function to blend `color` with `source_alpha` opacity onto `backdrop`.
+fn blend_pixel(
+    backdrop: Rgba<u8>,
+    color: Rgba<u8>,
+    source_alpha: f32,
+    blend: BlendMode,
+) -> Rgba<u8> {
+    let backdrop_alpha = backdrop[3] as f32 / 255.;
+    let out_alpha = source_alpha + backdrop_alpha * (1. - source_alpha);
+    if out_alpha <= 0. {
+        return backdrop;
+    }
+    let mut out = [0u8; 4];
+    for channel in 0..3 {
+        let backdrop_channel = backdrop[channel] as f32 / 255.;
+        let source_channel = color[channel] as f32 / 255.;
+        // Where the image is transparent there is nothing to blend with, so use the plain color.
+        let blended = (1. - backdrop_alpha) * source_channel
+            + backdrop_alpha * blend.blend_channel(backdrop_channel, source_channel);
+        let value = (source_alpha * blended
+            + (1. - source_alpha) * backdrop_alpha * backdrop_channel)
+            / out_alpha;
+        out[channel] = (value * 255.).round().clamp(0., 255.) as u8;
+    }
+    out[3] = (out_alpha * 255.).round() as u8;
+    Rgba(out)
+}
@@ -0,0 +1,134 @@
+//! Breaking wrapped words at proper hyphenation points. Enabled with the `hyphenation` feature.
+
+use std::path::Path;
+
+use hyphenation::{Hyphenator, Language, Load, Standard};
+use image::DynamicImage;
+
+use crate::{
+    get_text_height, get_text_width, line_widths, measure_lines, position_and_draw,
+    wrap_text_to_lines, FontBundle, TextJustify, TextMeasurement, TextOnImageError, VerticalAnchor,
+    WrapBehavior,
+};
+
+/// Hyphenation patterns for one language, built from the standard TeX pattern files.
+///
+/// Load a dictionary file from the `hyphenation` crate with [`HyphenationDict::load`], or
+/// convert a `hyphenation::Standard` that was loaded some other way, such as one embedded with
+/// that crate's `embed_*` features.
+#[derive(Debug)]
+pub struct HyphenationDict {
+    standard: Standard,
+}
+
+impl HyphenationDict {
+    /// Loads the dictionary for `language` from a `.standard.bincode` file.
+    pub fn load<P: AsRef<Path>>(language: Language, path: P) -> Result<Self, TextOnImageError> {
+        let standard =
+            Standard::from_path(language, path).map_err(TextOnImageError::HyphenationError)?;
+        Ok(HyphenationDict { standard })
+    }
+
+    /// Gets the byte offsets a word may be broken at, in increasing order.
+    pub fn breaks(&self, word: &str) -> Vec<usize> {
+        self.standard.hyphenate(word).breaks
+    }
+}
+
+impl From<Standard> for HyphenationDict {
+    fn from(standard: Standard) -> Self {
+        HyphenationDict { standard }
+    }
+}
+
+/// Draws text wrapped to `wrap_width`, splitting words that do not fit only where `dict` allows
+/// a hyphen.
+///
+/// Words the dictionary cannot break that are still too wide for a line of their own are split
+/// between any two characters, the same as `WrapBehavior::Wrap`.
+pub fn text_on_image_with_hyphenation<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_width: u32,
+    dict: &HyphenationDict,
+) -> TextMeasurement {
+    let lines = hyphenated_lines(text.as_ref(), font_bundle, wrap_width, dict);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let wrap_behavior = WrapBehavior::Wrap(wrap_width);
+    let measurement = measure_lines(
+        &line_widths(font_bundle, &lines),
+        get_text_height(font_bundle),
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    position_and_draw(
+        image,
+        lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    measurement
+}
+
+/// Helper function to wrap text, hyphenating words at the dictionary's break points.
+pub(crate) fn hyphenated_lines(
+    text: &str,
+    font_bundle: &FontBundle<'_>,
+    wrap_width: u32,
+    dict: &HyphenationDict,
+) -> Vec<String> {
+    let fits = |line: &str| get_text_width(font_bundle, line) <= wrap_width;
+    let join = |buffer: &str, word: &str| {
+        if buffer.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", buffer, word)
+        }
+    };
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut buffer = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut rest = word.to_string();
+            loop {
+                if fits(&join(&buffer, &rest)) {
+                    buffer = join(&buffer, &rest);
+                    break;
+                }
+                let best_break = dict
+                    .breaks(&rest)
+                    .into_iter()
+                    .rev()
+                    .find(|&index| fits(&join(&buffer, &format!("{}-", &rest[..index]))));
+                if let Some(index) = best_break {
+                    lines.push(join(&buffer, &format!("{}-", &rest[..index])));
+                    buffer.clear();
+                    rest = rest[index..].to_string();
+                } else if !buffer.is_empty() {
+                    lines.push(std::mem::take(&mut buffer));
+                } else {
+                    // Nowhere to hyphenate, so fall back to splitting between characters.
+                    let mut pieces =
+                        wrap_text_to_lines(&rest, font_bundle, &WrapBehavior::Wrap(wrap_width));
+                    buffer = pieces.pop().unwrap_or_default();
+                    lines.extend(pieces);
+                    break;
+                }
+            }
+        }
+        lines.push(buffer);
+    }
+    lines
+}
@@ -0,0 +1,124 @@
+//! Drawing text with random per-character offsets and rotations. Enabled with the `rand` feature.
+
+use image::{DynamicImage, GrayImage, Luma};
+use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+use rusttype::{point, vector};
+
+use crate::{
+    draw_glyph_coverage, effects, get_text_height, layout_glyphs, line_positions, line_widths,
+    measure_lines, wrap_text_to_lines, BlendMode, FontBundle, TextJustify, TextMeasurement,
+    VerticalAnchor, WrapBehavior,
+};
+
+/// How far [`text_on_image_with_jitter`] may move and turn each character.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JitterConfig {
+    /// The most pixels a character may move left or right.
+    pub max_x_offset: f32,
+    /// The most pixels a character may move up or down.
+    pub max_y_offset: f32,
+    /// The most degrees a character may turn either way about its center.
+    pub max_rotation_deg: f32,
+    /// Seeds the random numbers, so the same seed always gives the same result.
+    pub seed: u64,
+}
+
+/// Draws text with every character nudged and turned by a random amount, so it looks
+/// hand-written.
+///
+/// Offsets and rotations are picked uniformly up to the limits in `jitter`. The same seed gives
+/// the same picture with the same version of this crate. Lines are laid out as if there were no
+/// jitter, so the returned measurement does not include it.
+pub fn text_on_image_with_jitter<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    jitter: JitterConfig,
+) -> TextMeasurement {
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let widths = line_widths(font_bundle, &lines);
+    let line_height = get_text_height(font_bundle);
+    let positions = line_positions(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let mut rng = StdRng::seed_from_u64(jitter.seed);
+    let mut random_up_to = |max: f32| {
+        let max = max.abs();
+        rng.random_range(-max..=max)
+    };
+    let ascent = font_bundle.font.v_metrics(font_bundle.scale).ascent;
+    let mut text_mask = GrayImage::new(image.width(), image.height());
+    let kerning_only = |_, _, kerning| kerning;
+    for (&line, &(line_x, line_y)) in lines.iter().zip(&positions) {
+        let origin = point(line_x as f32, line_y as f32 + ascent);
+        for glyph in layout_glyphs(font_bundle, line, origin, &kerning_only) {
+            let offset = vector(
+                random_up_to(jitter.max_x_offset),
+                random_up_to(jitter.max_y_offset),
+            );
+            let rotation = random_up_to(jitter.max_rotation_deg);
+            let glyph = glyph
+                .clone()
+                .into_unpositioned()
+                .positioned(glyph.position() + offset);
+            let Some(bounding_box) = glyph.pixel_bounding_box() else {
+                continue;
+            };
+            // Room for the corners of the glyph to turn without being cut off.
+            let padding = bounding_box.width().max(bounding_box.height()) / 2 + 1;
+            let cell_left = bounding_box.min.x - padding;
+            let cell_top = bounding_box.min.y - padding;
+            let mut cell = GrayImage::new(
+                (bounding_box.width() + 2 * padding) as u32,
+                (bounding_box.height() + 2 * padding) as u32,
+            );
+            let glyph = glyph
+                .clone()
+                .into_unpositioned()
+                .positioned(glyph.position() - vector(cell_left as f32, cell_top as f32));
+            draw_glyph_coverage(&mut cell, &glyph);
+            if rotation != 0. {
+                cell = rotate_about_center(
+                    &cell,
+                    rotation.to_radians(),
+                    Interpolation::Bilinear,
+                    Luma([0]),
+                );
+            }
+            for (cell_x, cell_y, coverage) in cell.enumerate_pixels() {
+                let (mask_x, mask_y) = (cell_left + cell_x as i32, cell_top + cell_y as i32);
+                if coverage[0] > 0
+                    && (0..image.width() as i32).contains(&mask_x)
+                    && (0..image.height() as i32).contains(&mask_y)
+                {
+                    let pixel = text_mask.get_pixel_mut(mask_x as u32, mask_y as u32);
+                    pixel[0] = pixel[0].max(coverage[0]);
+                }
+            }
+        }
+    }
+    effects::composite_mask(image, &text_mask, font_bundle.color(), BlendMode::Normal);
+    measure_lines(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
@@ -0,0 +1,98 @@
+//! Reading text placement from JSON strings. Enabled with the `json` feature.
+
+use std::path::PathBuf;
+
+use image::{DynamicImage, Rgba};
+use rusttype::Scale;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    load_font, text_on_image, FontBundle, TextJustify, TextMeasurement, TextOnImageError,
+    VerticalAnchor, WrapBehavior,
+};
+
+/// Every parameter of [`text_on_image`], as read from JSON.
+///
+/// ```json
+/// {
+///     "text": "Hello",
+///     "font_path": "fonts/MyFont.ttf",
+///     "scale": 40.0,
+///     "color": "#00ff00",
+///     "x": 400,
+///     "y": 800,
+///     "justify": "Center",
+///     "anchor": "Top",
+///     "wrap_max_width": 250
+/// }
+/// ```
+///
+/// `color` is either a hex string (`"#rrggbb"` or `"#rrggbbaa"`) or an `[r, g, b, a]` array.
+/// `justify`, `anchor` and `wrap_max_width` are optional.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct JsonTextConfig {
+    pub text: String,
+    pub font_path: PathBuf,
+    pub scale: f32,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub color: [u8; 4],
+    pub x: i32,
+    pub y: i32,
+    #[serde(default)]
+    pub justify: TextJustify,
+    #[serde(default)]
+    pub anchor: VerticalAnchor,
+    #[serde(default)]
+    pub wrap_max_width: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonColor {
+    Hex(String),
+    Rgba([u8; 4]),
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 4], D::Error> {
+    match JsonColor::deserialize(deserializer)? {
+        JsonColor::Rgba(color) => Ok(color),
+        JsonColor::Hex(hex) => parse_hex_color(&hex)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid hex color \"{}\"", hex))),
+    }
+}
+
+/// Helper function to parse `#rrggbb` or `#rrggbbaa`, with or without the `#`.
+fn parse_hex_color(hex: &str) -> Option<[u8; 4]> {
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Some([channel(0)?, channel(2)?, channel(4)?, alpha])
+}
+
+/// Parses a [`JsonTextConfig`] from a JSON string and draws it on the image.
+pub fn text_on_image_from_json<J: AsRef<str>>(
+    image: &mut DynamicImage,
+    json: J,
+) -> Result<TextMeasurement, TextOnImageError> {
+    let config: JsonTextConfig =
+        serde_json::from_str(json.as_ref()).map_err(TextOnImageError::JsonError)?;
+    let font = load_font(&config.font_path)?;
+    let font_bundle = FontBundle::new(&font, Scale::uniform(config.scale), Rgba(config.color));
+    let wrap_behavior = match config.wrap_max_width {
+        Some(max_width) => WrapBehavior::Wrap(max_width),
+        None => WrapBehavior::NoWrap,
+    };
+    Ok(text_on_image(
+        image,
+        config.text,
+        &font_bundle,
+        config.x,
+        config.y,
+        config.justify,
+        config.anchor,
+        wrap_behavior,
+    ))
+}
@@ -1,27 +1,166 @@
 //! A library to make placing text on images easier. Extends draw_text_mut's functionality from [imageproc](https://docs.rs/imageproc/0.23.0/imageproc/index.html).
+//!
+//! With the `log` feature enabled, debug builds report how text is wrapped and positioned
+//! through the [log](https://docs.rs/log) crate. The `tracing` feature does the same through
+//! [tracing](https://docs.rs/tracing) instead. Any logger picks the output up, for example
+//! `env_logger` run with `RUST_LOG=text_on_image=trace`:
+//! ```no_run
+//! env_logger::init();
+//! // Calls into text_on_image now log their line widths and wrapping decisions.
+//! ```
+#![allow(clippy::too_many_arguments)]
 
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::path::Path;
 
-use image::{DynamicImage, ImageError, Rgba};
-use imageproc::drawing::draw_text_mut;
-use rusttype::{point, Font, Scale};
+use image::{DynamicImage, GenericImage, GenericImageView, ImageError, Rgba};
+use imageproc::drawing::{draw_filled_ellipse_mut, draw_filled_rect_mut, draw_text_mut};
+use imageproc::filter::gaussian_blur_f32;
+use imageproc::pixelops::weighted_sum;
+use imageproc::rect::Rect;
+use rusttype::{point, Font, GlyphId, Point, PositionedGlyph, Scale};
+
+/// Logs layout details through `tracing` or `log` in debug builds, whichever feature is enabled.
+/// Compiles to nothing in release builds or when neither feature is enabled.
+macro_rules! debug_log {
+    ($($arg:tt)*) => {
+        #[cfg(all(debug_assertions, feature = "tracing"))]
+        tracing::debug!($($arg)*);
+        #[cfg(all(debug_assertions, feature = "log", not(feature = "tracing")))]
+        log::debug!($($arg)*);
+    };
+}
+
+/// Like `debug_log!`, but for the very chatty per-word wrapping decisions.
+macro_rules! trace_log {
+    ($($arg:tt)*) => {
+        #[cfg(all(debug_assertions, feature = "tracing"))]
+        tracing::trace!($($arg)*);
+        #[cfg(all(debug_assertions, feature = "log", not(feature = "tracing")))]
+        log::trace!($($arg)*);
+    };
+}
 
 #[derive(Debug)]
 pub enum TextOnImageError {
     ImageError(ImageError),
+    IoError(std::io::Error),
+    /// The font data could not be parsed.
+    InvalidFont,
+    /// No font exists with this name or path.
+    FontNotFound {
+        name: String,
+    },
+    /// A number did not match any variant of the named enum.
+    UnknownVariant {
+        type_name: &'static str,
+        value: u8,
+    },
+    /// The text does not fit in the requested space even at the smallest allowed font scale.
+    CannotFitText,
+    /// A template placeholder has no value and `MissingKeyPolicy::Error` was asked for.
+    MissingPlaceholder {
+        key: String,
+    },
+    /// A word is wider than a `WrapBehavior::WrapStrictNoHyphen` line.
+    WordTooWide {
+        word: String,
+        width: u32,
+        max_width: u32,
+    },
+    /// The wrap settings cannot lay out any text: a `WrapBehavior::Wrap` or
+    /// `WrapBehavior::WrapOptimal` width below 2 ems, or `WrapBehavior::WrapAtCharsPreferWords(0)`.
+    InvalidWrap(WrapBehavior),
+    /// The image could not be encoded, with the encoder's reason.
+    EncodingError(String),
+    #[cfg(feature = "config")]
+    ConfigError(toml::de::Error),
+    #[cfg(feature = "json")]
+    JsonError(serde_json::Error),
+    #[cfg(feature = "hyphenation")]
+    HyphenationError(hyphenation::load::Error),
+}
+
+impl From<ImageError> for TextOnImageError {
+    fn from(err: ImageError) -> Self {
+        TextOnImageError::ImageError(err)
+    }
+}
+
+impl From<std::io::Error> for TextOnImageError {
+    fn from(err: std::io::Error) -> Self {
+        TextOnImageError::IoError(err)
+    }
 }
 
 /// Defines how the text extends from the point you place it.
-#[derive(Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
 pub enum TextJustify {
     Left,
     #[default]
     Center,
     Right,
+    /// Stretches every line but the last to the wrap width, or to the widest line when not
+    /// wrapping. The last line is left aligned.
+    JustifyLastLeft,
+    /// Stretches every line but the last like `JustifyLastLeft`, but right aligns the last line.
+    JustifyLastRight,
+    /// Stretches every line but the last like `JustifyLastLeft`, but centers the last line in
+    /// the text's width. Text with a single line is that last line, so it is centered.
+    JustifyLastCenter,
+}
+
+/// Converts from the numbers used by compact config formats: 0 is `Left`, 1 is `Center`,
+/// 2 is `Right`, 3 is `JustifyLastLeft`, 4 is `JustifyLastRight` and 5 is `JustifyLastCenter`.
+impl TryFrom<u8> for TextJustify {
+    type Error = TextOnImageError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(TextJustify::Left),
+            1 => Ok(TextJustify::Center),
+            2 => Ok(TextJustify::Right),
+            3 => Ok(TextJustify::JustifyLastLeft),
+            4 => Ok(TextJustify::JustifyLastRight),
+            5 => Ok(TextJustify::JustifyLastCenter),
+            _ => Err(TextOnImageError::UnknownVariant {
+                type_name: "TextJustify",
+                value,
+            }),
+        }
+    }
+}
+
+impl From<TextJustify> for u8 {
+    fn from(justify: TextJustify) -> Self {
+        match justify {
+            TextJustify::Left => 0,
+            TextJustify::Center => 1,
+            TextJustify::Right => 2,
+            TextJustify::JustifyLastLeft => 3,
+            TextJustify::JustifyLastRight => 4,
+            TextJustify::JustifyLastCenter => 5,
+        }
+    }
+}
+
+impl TextJustify {
+    /// Whether lines are stretched to fill the text's width.
+    fn is_full_justify(self) -> bool {
+        matches!(
+            self,
+            TextJustify::JustifyLastLeft
+                | TextJustify::JustifyLastRight
+                | TextJustify::JustifyLastCenter
+        )
+    }
 }
 
 /// Defines where the text sits relative to the vertical coordinate provided.
-#[derive(Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
 pub enum VerticalAnchor {
     Top,
     #[default]
@@ -29,20 +168,620 @@ pub enum VerticalAnchor {
     Bottom,
 }
 
+/// Converts from the numbers used by compact config formats: 0 is `Top`, 1 is `Center` and
+/// 2 is `Bottom`.
+impl TryFrom<u8> for VerticalAnchor {
+    type Error = TextOnImageError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(VerticalAnchor::Top),
+            1 => Ok(VerticalAnchor::Center),
+            2 => Ok(VerticalAnchor::Bottom),
+            _ => Err(TextOnImageError::UnknownVariant {
+                type_name: "VerticalAnchor",
+                value,
+            }),
+        }
+    }
+}
+
+impl From<VerticalAnchor> for u8 {
+    fn from(anchor: VerticalAnchor) -> Self {
+        match anchor {
+            VerticalAnchor::Top => 0,
+            VerticalAnchor::Center => 1,
+            VerticalAnchor::Bottom => 2,
+        }
+    }
+}
+
 /// Choose whether text wraps if it would extend beyond a specified pixel length.
-#[derive(Default)]
+///
+/// Unlike [`TextJustify`] and [`VerticalAnchor`], this does not implement `TryFrom<u8>` because
+/// a single number cannot hold the width carried by `Wrap`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum WrapBehavior {
     #[default]
     NoWrap,
     Wrap(u32),
+    /// Wraps like `Wrap`, but picks line breaks that keep line widths as even as possible.
+    /// See [`wrap_text_greedy_optimal`].
+    WrapOptimal(u32),
+    /// Wraps lines at this many characters instead of a pixel width, breaking at the last space
+    /// that fits or mid-word if there is none. Suits text whose line lengths are specified in
+    /// characters, such as translated subtitles.
+    WrapAtCharsPreferWords(usize),
+    /// Wraps like `Wrap`, but only ever breaks between words. A word wider than the width is
+    /// left on a line of its own that overflows, and [`text_on_image_try`] returns
+    /// `TextOnImageError::WordTooWide` for it instead of drawing.
+    WrapStrictNoHyphen(u32),
 }
 impl WrapBehavior {
     pub fn new(max_width: u32) -> Self {
         WrapBehavior::Wrap(max_width)
     }
+
+    /// The pixel width lines are wrapped to, if any.
+    fn max_width(&self) -> Option<u32> {
+        match *self {
+            WrapBehavior::NoWrap | WrapBehavior::WrapAtCharsPreferWords(_) => None,
+            WrapBehavior::Wrap(max_width)
+            | WrapBehavior::WrapOptimal(max_width)
+            | WrapBehavior::WrapStrictNoHyphen(max_width) => Some(max_width),
+        }
+    }
+}
+
+/// The area covered by drawn text, in image coordinates.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TextMeasurement {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    pub line_count: usize,
+}
+
+/// Where a single drawn line ended up, in image coordinates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMetrics {
+    pub text: String,
+    pub index: usize,
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    /// The y coordinate glyphs sit on.
+    pub baseline: i32,
+}
+
+/// A glyph handed to the callback of [`text_on_image_with_glyph_callback`] to draw.
+#[derive(Debug, Clone)]
+pub struct GlyphRenderInfo<'a> {
+    /// The glyph positioned where [`text_on_image`] would draw it.
+    pub glyph: PositionedGlyph<'a>,
+    pub char: char,
+    pub line_index: usize,
+    /// The index of the character in its line.
+    pub char_index: usize,
+    /// The left end of the glyph's baseline, rounded to whole pixels.
+    pub draw_x: i32,
+    pub draw_y: i32,
+}
+
+/// The pixels text will paint, in image coordinates. `right` and `bottom` are exclusive.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TextBoundingBox {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl TextBoundingBox {
+    pub fn width(&self) -> u32 {
+        (self.right - self.left).max(0) as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        (self.bottom - self.top).max(0) as u32
+    }
+
+    /// Whether the two boxes share any pixels.
+    pub fn intersects(&self, other: &TextBoundingBox) -> bool {
+        self.left < other.right
+            && other.left < self.right
+            && self.top < other.bottom
+            && other.top < self.bottom
+    }
+}
+
+/// Everything [`text_on_image_draw_debug_v2`] worked out while drawing, for inspecting a layout.
+///
+/// Its `Display` implementation prints a readable summary for the terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugInfo {
+    /// The coordinates the text was positioned from, marked with a cross.
+    pub anchor: (i32, i32),
+    pub bounding_box: TextBoundingBox,
+    pub lines: Vec<LineMetrics>,
+    pub wrapped_lines: Vec<String>,
+}
+
+/// The complete layout of some text, from [`text_on_image_measure_only`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FullTextLayout {
+    /// The pixels the glyphs would paint.
+    pub bounding_box: TextBoundingBox,
+    pub lines: Vec<LineMetrics>,
+    pub wrapped_text: Vec<String>,
+    /// The width of the line boxes, as [`measure_wrapped_text`] gives it.
+    pub total_width: u32,
+    /// The height of the line boxes, as [`measure_wrapped_text`] gives it.
+    pub total_height: u32,
+}
+
+impl Display for DebugInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "anchor: ({}, {})", self.anchor.0, self.anchor.1)?;
+        let b = &self.bounding_box;
+        writeln!(
+            f,
+            "bounding box: ({}, {}) to ({}, {}), {}x{}",
+            b.left,
+            b.top,
+            b.right,
+            b.bottom,
+            b.width(),
+            b.height()
+        )?;
+        writeln!(f, "lines: {}", self.lines.len())?;
+        for line in &self.lines {
+            writeln!(
+                f,
+                "  {}: {:?} at ({}, {}), {}x{}, baseline {}",
+                line.index, line.text, line.left, line.top, line.width, line.height, line.baseline
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The size of one character at a font bundle's scale, from [`FontBundle::measure_char`].
+///
+/// The bounds are relative to the glyph's origin on the baseline, with y growing downward, so
+/// `min_y` is negative for the part above the baseline and `max_y` is the descender depth.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CharMetrics {
+    pub advance_width: f32,
+    pub min_x: f32,
+    pub max_x: f32,
+    pub min_y: f32,
+    pub max_y: f32,
+}
+
+/// How much text [`text_on_image_safe`] could not draw in full because it ran off the image.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ClipInfo {
+    /// Lines with at least one clipped character.
+    pub lines_clipped: usize,
+    /// Characters drawn only partly, or not at all, because they cross the image border.
+    pub chars_clipped: usize,
+}
+
+/// The text shown in one frame of [`text_on_image_animated_frames`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnimationFrame {
+    pub text: String,
+    pub x: i32,
+    pub y: i32,
+    pub justify: TextJustify,
+    pub anchor: VerticalAnchor,
+    pub delay_ms: u32,
+}
+
+/// A rendered animation frame and how long it should be shown.
+///
+/// Converts into an [`image::Frame`] for encoding with `image::codecs::gif`.
+#[derive(Debug, Clone)]
+pub struct FramedImage {
+    pub image: DynamicImage,
+    pub delay_ms: u32,
+}
+
+impl From<FramedImage> for image::Frame {
+    fn from(framed: FramedImage) -> Self {
+        image::Frame::from_parts(
+            framed.image.into_rgba8(),
+            0,
+            0,
+            image::Delay::from_numer_denom_ms(framed.delay_ms, 1),
+        )
+    }
+}
+
+/// Which way [`text_on_image_marquee`] scrolls text across the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollDirection {
+    LeftToRight,
+    RightToLeft,
+    TopToBottom,
+    BottomToTop,
+}
+
+/// How [`text_on_image_marquee`] scrolls text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarqueeConfig {
+    pub frame_count: usize,
+    /// How far the text moves each frame. Negative values scroll against `direction`.
+    pub scroll_speed_px_per_frame: i32,
+    pub direction: ScrollDirection,
+    /// Blended over the base image in every frame, behind the text. Use a transparent color to
+    /// leave the base image as it is.
+    pub background: Rgba<u8>,
+}
+
+/// How [`text_on_image_animated_reveal`] speeds up or slows down the reveal over time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EaseFunction {
+    Linear,
+    /// Starts slowly and speeds up.
+    EaseIn,
+    /// Starts quickly and slows down.
+    EaseOut,
+    /// Starts and ends slowly.
+    EaseInOut,
+    /// A CSS style `cubic-bezier(x1, y1, x2, y2)` curve from (0, 0) to (1, 1).
+    CubicBezier(f32, f32, f32, f32),
+}
+
+impl EaseFunction {
+    /// Maps how far through the animation it is, from 0 to 1, to how far through the reveal it
+    /// is. Progress outside 0 to 1 is clamped.
+    pub fn ease(self, progress: f32) -> f32 {
+        let t = progress.clamp(0., 1.);
+        let eased = match self {
+            EaseFunction::Linear => t,
+            EaseFunction::EaseIn => t * t * t,
+            EaseFunction::EaseOut => 1. - (1. - t).powi(3),
+            EaseFunction::EaseInOut => {
+                if t < 0.5 {
+                    4. * t * t * t
+                } else {
+                    1. - (2. - 2. * t).powi(3) / 2.
+                }
+            }
+            EaseFunction::CubicBezier(x1, y1, x2, y2) => {
+                let bezier = |t: f32, p1: f32, p2: f32| {
+                    3. * (1. - t) * (1. - t) * t * p1 + 3. * (1. - t) * t * t * p2 + t * t * t
+                };
+                // x grows with the curve parameter when x1 and x2 are in 0 to 1, so bisect for it.
+                let (x1, x2) = (x1.clamp(0., 1.), x2.clamp(0., 1.));
+                let (mut low, mut high) = (0f32, 1f32);
+                for _ in 0..32 {
+                    let middle = (low + high) / 2.;
+                    if bezier(middle, x1, x2) < t {
+                        low = middle;
+                    } else {
+                        high = middle;
+                    }
+                }
+                bezier((low + high) / 2., y1, y2)
+            }
+        };
+        eased.clamp(0., 1.)
+    }
+}
+
+/// What [`text_on_image_with_placeholder`] does with a `{key}` that has no value.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MissingKeyPolicy {
+    /// Draws the placeholder as written, braces and all.
+    #[default]
+    Leave,
+    /// Returns `TextOnImageError::MissingPlaceholder` without drawing anything.
+    Error,
+    /// Removes the placeholder.
+    ReplaceWithEmpty,
+}
+
+/// Kerning in pixels to use for specific character pairs instead of the font's own kerning.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct KerningOverrides {
+    pub pairs: HashMap<(char, char), f32>,
+}
+
+/// Characters to draw in place of others, such as letters standing in for the private use
+/// codepoints of an icon font.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GlyphSubstitutionMap {
+    pub substitutions: HashMap<char, char>,
+}
+
+/// Spacing and glyph rotation for [`text_on_image_vertical`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct VerticalTextConfig {
+    /// Extra pixels between each character and the next one down. May be negative.
+    pub char_spacing_px: i32,
+    /// Clockwise rotation applied to every glyph, such as 90.0 for Latin text in a vertical layout.
+    pub rotation_deg: f32,
+}
+
+/// The size of every character cell for [`text_on_image_monospace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonospaceConfig {
+    pub cell_width: u32,
+    pub cell_height: u32,
+}
+
+/// The circle [`text_on_image_in_circle`] fits text inside.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircleRegion {
+    pub center_x: i32,
+    pub center_y: i32,
+    pub radius: u32,
+}
+
+/// The gutter [`text_on_image_with_line_numbers_margin`] numbers lines in.
+#[derive(Clone, Copy)]
+pub struct LineNumberConfig<'a> {
+    /// The number given to the first line.
+    pub start: usize,
+    /// The font bundle line numbers are drawn with, often smaller or dimmer than the text.
+    pub font_bundle: FontBundle<'a>,
+    /// Pixels reserved left of the text for line numbers.
+    pub gutter_width: u32,
+    /// Draws a one pixel line in the rightmost column of the gutter if given.
+    pub separator_color: Option<Rgba<u8>>,
+}
+
+/// Moves a range of characters off the baseline in [`text_on_image_with_baseline_shifts`].
+///
+/// The range covers `start_char` up to but not including `end_char`. `shift_px` is added to the
+/// characters' y coordinates, so negative values raise them like a superscript and positive
+/// values lower them like a subscript.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BaselineShift {
+    pub start_char: usize,
+    pub end_char: usize,
+    pub shift_px: i32,
+}
+
+/// How [`text_on_image_with_paragraphs`] sets off paragraphs from each other.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParagraphConfig {
+    /// How far the first line of every paragraph is moved right.
+    pub first_line_indent_px: u32,
+    /// The extra gap between the last line of a paragraph and the first line of the next.
+    pub paragraph_spacing_px: u32,
+}
+
+/// The text caret drawn by [`text_on_image_with_cursor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorConfig {
+    /// The character the cursor sits just before. Indexes past the end put it after the text.
+    pub char_index: usize,
+    pub color: Rgba<u8>,
+    /// Width of the cursor in pixels.
+    pub width: u32,
+    /// Whether the cursor is drawn at all, for animation frames where a blinking cursor is off.
+    pub blink_visible: bool,
+}
+
+/// Colors and spacing for the pill shaped background drawn by [`text_on_image_pill`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PillConfig {
+    pub background: Rgba<u8>,
+    pub border_color: Rgba<u8>,
+    pub border_width: u32,
+    pub h_padding: u32,
+    pub v_padding: u32,
+}
+
+/// The area text flows through in [`text_on_image_columns`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnConfig {
+    pub num_columns: u32,
+    pub column_width: u32,
+    pub column_gap: u32,
+    pub origin_x: i32,
+    pub origin_y: i32,
+    pub max_height: u32,
+}
+
+/// The result of flowing text through columns with [`text_on_image_columns`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnLayout {
+    pub columns_used: u32,
+    pub text_truncated: bool,
+    /// The lines that did not fit in any column, joined by newlines.
+    pub remaining_text: Option<String>,
+}
+
+/// Which part of the text [`text_on_image_ellipsize`] replaces with the ellipsis.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EllipsisPosition {
+    /// Keeps the end of the text, which suits file paths: `…/foo/bar.txt`.
+    Start,
+    /// Keeps both ends of the text, which suits phone numbers and identifiers.
+    Middle,
+    #[default]
+    End,
+}
+
+/// Where [`text_on_image_align_to`] places text relative to a previous [`TextMeasurement`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativeAlignment {
+    /// Left edge `gap` pixels after the reference's right edge, tops aligned.
+    RightOf { gap: u32 },
+    /// Top edge `gap` pixels below the reference's bottom edge, left edges aligned.
+    Below { gap: u32 },
+    /// Right edge `gap` pixels before the reference's left edge, tops aligned.
+    LeftOf { gap: u32 },
+    /// Bottom edge `gap` pixels above the reference's top edge, left edges aligned.
+    Above { gap: u32 },
+    /// Centered on the reference's center point.
+    CenterAlignedWith,
+}
+
+/// A copy of the text drawn behind it at an offset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowConfig {
+    pub offset_x: i32,
+    pub offset_y: i32,
+    pub color: Rgba<u8>,
+    /// Gaussian blur sigma for a soft shadow. `None` keeps the shadow's edges sharp.
+    pub blur_radius: Option<f32>,
+}
+
+impl ShadowConfig {
+    /// A sharp shadow `distance_px` pixels away in the direction of `angle_deg`, measured
+    /// clockwise from pointing right, so 0 puts the shadow to the right and 90 puts it below.
+    pub fn from_angle(angle_deg: f32, distance_px: f32, color: Rgba<u8>) -> ShadowConfig {
+        let (sin, cos) = angle_deg.to_radians().sin_cos();
+        ShadowConfig {
+            offset_x: (distance_px * cos).round() as i32,
+            offset_y: (distance_px * sin).round() as i32,
+            color,
+            blur_radius: None,
+        }
+    }
+}
+
+/// A shadow placed by direction and distance, as in design tools, instead of by offset.
+///
+/// `angle_deg` is clockwise from pointing right, so 0 puts the shadow to the right and 90 puts
+/// it below.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AngleShadowConfig {
+    pub angle_deg: f32,
+    pub distance_px: f32,
+    pub color: Rgba<u8>,
+}
+
+impl From<AngleShadowConfig> for ShadowConfig {
+    fn from(shadow: AngleShadowConfig) -> Self {
+        ShadowConfig::from_angle(shadow.angle_deg, shadow.distance_px, shadow.color)
+    }
+}
+
+/// A flat design "long shadow" made of copies of the text stacked one pixel apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LongShadowConfig {
+    /// The direction the shadow falls in, clockwise from pointing right, so 90 points down.
+    pub angle_deg: f32,
+    /// How many pixels the shadow reaches from the text.
+    pub length: u32,
+    pub color: Rgba<u8>,
+    /// Whether the shadow fades out toward its far end.
+    pub fade: bool,
+}
+
+/// A solid border drawn `width` pixels out from the edges of each glyph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutlineConfig {
+    pub width: u32,
+    pub color: Rgba<u8>,
+}
+
+/// Two borders around each glyph, `outer` drawn outside `inner`.
+///
+/// The widths add up, so `outer` reaches `inner.width + outer.width` pixels from the glyph edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DoubleOutlineConfig {
+    pub inner: OutlineConfig,
+    pub outer: OutlineConfig,
+}
+
+/// A rectangle blended behind the whole block of text, `padding` pixels larger on every side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackgroundConfig {
+    pub color: Rgba<u8>,
+    pub padding: u32,
+}
+
+/// Row backgrounds behind alternating lines of text, for subtitles and tables.
+///
+/// The first line counts as even.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZebraConfig {
+    pub even_color: Rgba<u8>,
+    pub odd_color: Rgba<u8>,
+    /// How far the rows reach past the left and right of the widest line.
+    pub padding_x: u32,
+    /// How far the first row reaches above the text, and the last row below it.
+    pub padding_y: u32,
+}
+
+/// The way a [`GradientBackground`] runs from its start color to its end color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GradientDirection {
+    /// From the left edge to the right edge.
+    Horizontal,
+    /// From the top edge to the bottom edge.
+    Vertical,
+    /// From the top left corner to the bottom right corner.
+    Diagonal,
+}
+
+/// A linear gradient blended behind the whole block of text, `padding` pixels larger on every
+/// side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GradientBackground {
+    pub start_color: Rgba<u8>,
+    pub end_color: Rgba<u8>,
+    pub direction: GradientDirection,
+    pub padding: u32,
+}
+
+/// A repeating pattern for [`text_on_image_with_tile_background`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TilePattern {
+    /// Squares `size` pixels wide, starting with `color_a` in the top left corner.
+    Checkerboard {
+        size: u32,
+        color_a: Rgba<u8>,
+        color_b: Rgba<u8>,
+    },
+    /// Stripes `width` pixels wide, turned `angle_deg` degrees clockwise from horizontal, so 90
+    /// gives vertical stripes.
+    Stripes {
+        width: u32,
+        angle_deg: f32,
+        color_a: Rgba<u8>,
+        color_b: Rgba<u8>,
+    },
+}
+
+/// How [`text_on_image_with_border`] draws the lines of its border.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorderStyle {
+    Solid,
+    /// Dashes `dash_len` pixels long with `gap_len` pixels between them.
+    Dashed {
+        dash_len: u32,
+        gap_len: u32,
+    },
+    /// Square dots as wide as the border, one dot width apart.
+    Dotted,
+}
+
+/// A line under each line of text, `offset` pixels below the baseline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnderlineConfig {
+    pub color: Rgba<u8>,
+    pub thickness: u32,
+    pub offset: i32,
 }
 
 /// A bundle of font related values.
+///
+/// Text is always laid out one character at a time with [`Font::glyph`], since rusttype does not
+/// substitute ligatures. Pairs such as `fi` are drawn as separate glyphs, so character positions
+/// from [`get_char_positions`] always line up with what is drawn, at the cost of the joined
+/// forms some fonts offer.
+#[derive(Clone, Copy)]
 pub struct FontBundle<'a> {
     font: &'a Font<'a>,
     scale: Scale,
@@ -81,9 +820,141 @@ impl<'a> FontBundle<'a> {
     pub fn set_color(&mut self, color_: Rgba<u8>) {
         self.color = color_;
     }
+
+    pub fn scale(&self) -> Scale {
+        self.scale
+    }
+
+    pub fn color(&self) -> Rgba<u8> {
+        self.color
+    }
+
+    /// Returns a copy of this bundle with a different color.
+    pub fn with_color(&self, color_: Rgba<u8>) -> Self {
+        let mut font_bundle = *self;
+        font_bundle.set_color(color_);
+        font_bundle
+    }
+
+    /// Returns a copy of this bundle with the same red, green and blue but a new alpha.
+    pub fn with_color_alpha(&self, alpha: u8) -> Self {
+        let mut font_bundle = *self;
+        font_bundle.set_color_alpha(alpha);
+        font_bundle
+    }
+
+    /// Changes only the alpha channel of this bundle's color.
+    pub fn set_color_alpha(&mut self, alpha: u8) {
+        self.color.0[3] = alpha;
+    }
+
+    /// Returns the advance width and ink bounds of a single character at this bundle's scale.
+    ///
+    /// Characters the font has no glyph for get all zero metrics. Characters that draw nothing,
+    /// like a space, keep their advance width but have zero bounds.
+    pub fn measure_char(&self, c: char) -> CharMetrics {
+        let glyph = self.font.glyph(c);
+        if glyph.id() == GlyphId(0) {
+            return CharMetrics::default();
+        }
+        let glyph = glyph.scaled(self.scale);
+        let advance_width = glyph.h_metrics().advance_width;
+        match glyph.exact_bounding_box() {
+            Some(bounds) => CharMetrics {
+                advance_width,
+                min_x: bounds.min.x,
+                max_x: bounds.max.x,
+                min_y: bounds.min.y,
+                max_y: bounds.max.y,
+            },
+            None => CharMetrics {
+                advance_width,
+                ..CharMetrics::default()
+            },
+        }
+    }
+}
+
+/// A 16 pixel, black bundle using the bundled Bitstream Vera Sans Mono Bold font.
+///
+/// Only available with the `default-font` feature. Bitstream Vera is not public domain, but its
+/// license allows the font to be redistributed and embedded in software as long as it is not
+/// sold on its own.
+#[cfg(feature = "default-font")]
+impl Default for FontBundle<'static> {
+    fn default() -> Self {
+        static DEFAULT_FONT: std::sync::OnceLock<Font<'static>> = std::sync::OnceLock::new();
+        let font = DEFAULT_FONT.get_or_init(|| {
+            Font::try_from_bytes(include_bytes!(
+                "../assets/BitstreamVeraSansMonoBold-pq1a.ttf"
+            ))
+            .expect("text_on_image: The bundled default font could not be parsed!")
+        });
+        FontBundle::new(font, Scale::uniform(16.0), Rgba([0, 0, 0, 255]))
+    }
+}
+
+/// Keeps a font bundle and layout options together for drawing many pieces of text the same way.
+pub struct TextRenderer<'a> {
+    font_bundle: FontBundle<'a>,
+    justify: TextJustify,
+    anchor: VerticalAnchor,
+    wrap: WrapBehavior,
+}
+
+impl<'a> TextRenderer<'a> {
+    pub fn new(
+        font_bundle: FontBundle<'a>,
+        justify: TextJustify,
+        anchor: VerticalAnchor,
+        wrap: WrapBehavior,
+    ) -> Self {
+        TextRenderer {
+            font_bundle,
+            justify,
+            anchor,
+            wrap,
+        }
+    }
+
+    /// Draws text at the given coordinates using this renderer's configuration.
+    pub fn render<T: AsRef<str>>(
+        &self,
+        image: &mut DynamicImage,
+        text: T,
+        pixels_from_left: i32,
+        pixels_from_top: i32,
+    ) -> TextMeasurement {
+        text_on_image(
+            image,
+            text,
+            &self.font_bundle,
+            pixels_from_left,
+            pixels_from_top,
+            self.justify,
+            self.anchor,
+            self.wrap,
+        )
+    }
+
+    /// Draws each `(text, pixels_from_left, pixels_from_top)` item in order.
+    pub fn render_batch<T: AsRef<str>>(
+        &self,
+        image: &mut DynamicImage,
+        items: &[(T, i32, i32)],
+    ) -> Vec<TextMeasurement> {
+        items
+            .iter()
+            .map(|(text, pixels_from_left, pixels_from_top)| {
+                self.render(image, text, *pixels_from_left, *pixels_from_top)
+            })
+            .collect()
+    }
 }
 
 /// Draws text on an image with support for text jusification, vertical anchor, and line wrapping.
+///
+/// Returns the area covered by the drawn text.
 pub fn text_on_image<T: AsRef<str>>(
     image: &mut DynamicImage,
     text: T,
@@ -93,58 +964,179 @@ pub fn text_on_image<T: AsRef<str>>(
     horizontal_justify: TextJustify,
     vertical_anchor: VerticalAnchor,
     wrap_behavior: WrapBehavior,
-) {
+) -> TextMeasurement {
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let measurement = measure_lines(
+        &line_widths(font_bundle, &lines),
+        get_text_height(font_bundle),
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    position_and_draw(
+        image,
+        lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    measurement
+}
+
+/// Draws text like [`text_on_image`] onto a copy of the image, leaving the original untouched.
+pub fn text_on_image_immutable<T: AsRef<str>>(
+    image: &DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> DynamicImage {
+    let mut image = image.clone();
+    text_on_image(
+        &mut image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    image
+}
+
+/// Draws text like [`text_on_image`] onto a copy of the image and encodes the result as lossy
+/// WebP in memory. Only available with the `webp` feature.
+///
+/// `quality` runs from 0.0 for the smallest file to 100.0 for the best looking one, and is
+/// clamped to that range.
+#[cfg(feature = "webp")]
+pub fn text_on_image_to_webp_bytes<T: AsRef<str>>(
+    image: &DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    quality: f32,
+) -> Result<Vec<u8>, TextOnImageError> {
+    let image = text_on_image_immutable(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+    .into_rgba8();
+    let encoder = webp::Encoder::from_rgba(image.as_raw(), image.width(), image.height());
+    let encoded = encoder
+        .encode_simple(false, quality.clamp(0., 100.))
+        .map_err(|err| TextOnImageError::EncodingError(format!("{:?}", err)))?;
+    Ok(encoded.to_vec())
+}
+
+/// Splits text into the lines that would be drawn, applying the wrap behavior.
+pub fn wrap_text_to_lines<T: AsRef<str>>(
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    wrap_behavior: &WrapBehavior,
+) -> Vec<String> {
+    wrap_lines_with(text, wrap_behavior, |line| {
+        get_text_width(font_bundle, line)
+    })
+}
+
+/// Wraps text to `max_width` like `WrapBehavior::Wrap` and joins the lines with `\n`, so the
+/// result can be stored and later drawn with `WrapBehavior::NoWrap` to get the same lines.
+pub fn insert_auto_newlines<T: AsRef<str>>(
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    max_width: u32,
+) -> String {
+    wrap_text_to_lines(text, font_bundle, &WrapBehavior::Wrap(max_width)).join("\n")
+}
+
+/// Helper function to wrap text using a custom measurement of each candidate line's width.
+fn wrap_lines_with<T: AsRef<str>, F: Fn(&str) -> u32>(
+    text: T,
+    wrap_behavior: &WrapBehavior,
+    text_width: F,
+) -> Vec<String> {
     let lines: Vec<&str> = text.as_ref().lines().map(|line| line.trim()).collect();
-    match wrap_behavior {
-        WrapBehavior::NoWrap => position_and_draw(
-            image,
-            lines,
-            font_bundle,
-            pixels_from_left,
-            pixels_from_top,
-            horizontal_justify,
-            vertical_anchor,
-        ),
+    match *wrap_behavior {
+        WrapBehavior::NoWrap => lines.iter().map(|&line| line.to_string()).collect(),
+        WrapBehavior::WrapAtCharsPreferWords(max_chars) => {
+            if max_chars == 0 {
+                panic!("text_on_image: Cannot wrap lines at 0 characters!");
+            }
+            lines
+                .iter()
+                .flat_map(|&line| wrap_paragraph_at_chars(line, max_chars))
+                .collect()
+        }
+        WrapBehavior::WrapStrictNoHyphen(max_width) => lines
+            .iter()
+            .flat_map(|&line| wrap_paragraph_at_words(line, max_width, &text_width))
+            .collect(),
+        WrapBehavior::WrapOptimal(max_width) => {
+            if max_width < text_width("mm") {
+                panic!("text_on_image: Cannot set max_width for wrapping below 2 ems! Try setting max_width to at least {}", text_width("mm"));
+            }
+            lines
+                .iter()
+                .flat_map(|&line| wrap_paragraph_optimal(line, max_width, &text_width))
+                .collect()
+        }
         WrapBehavior::Wrap(max_width) => {
-            if max_width < get_text_width(font_bundle, "mm") {
-                panic!("text_on_image: Cannot set max_width for wrapping below 2 ems! Try setting max_width to at least {}", get_text_width(font_bundle, "mm"));
+            if max_width < text_width("mm") {
+                panic!("text_on_image: Cannot set max_width for wrapping below 2 ems! Try setting max_width to at least {}", text_width("mm"));
             }
             let mut lines_altered: Vec<String> = vec![];
             for &line in &lines {
                 let mut buffer: String = String::new();
                 for word in line.split_whitespace() {
-                    if cfg!(debug_assertions) {
-                        println!(
-                            "\"{}\" has width {}. Compare to max_width {}",
-                            buffer.clone() + " " + word,
-                            get_text_width(font_bundle, buffer.clone() + " " + word),
-                            max_width
-                        );
-                    }
+                    trace_log!(
+                        "\"{}\" has width {}. Compare to max_width {}",
+                        buffer.clone() + " " + word,
+                        text_width(&(buffer.clone() + " " + word)),
+                        max_width
+                    );
                     let optional_space_width: u32 = if buffer.is_empty() {
-                        get_text_width(font_bundle, " ")
+                        text_width(" ")
                     } else {
                         0
                     };
-                    if get_text_width(font_bundle, buffer.clone() + " " + word)
+                    if text_width(&(buffer.clone() + " " + word))
                         <= max_width + optional_space_width
                     {
                         //Add word to line
-                        if cfg!(debug_assertions) {
-                            println!("Word {} gets added to line", word);
-                        }
+                        trace_log!("Word {} gets added to line", word);
                         if buffer.is_empty() {
                             buffer += word;
                         } else {
                             buffer = buffer + " " + word;
                         }
-                    } else if get_text_width(font_bundle, buffer.clone() + " " + word) > max_width
+                    } else if text_width(&(buffer.clone() + " " + word)) > max_width
                         && buffer.is_empty()
                     {
                         //add partial word with a dash at the end
                         let word_chars = word.chars();
                         for word_char in word_chars {
-                            if get_text_width(font_bundle, buffer.clone() + "-") <= max_width {
+                            if text_width(&(buffer.clone() + "-")) <= max_width {
                                 buffer = buffer + &word_char.to_string();
                             } else {
                                 buffer += "-";
@@ -153,18 +1145,16 @@ pub fn text_on_image<T: AsRef<str>>(
                                 buffer = buffer + &word_char.to_string();
                             }
                         }
-                    } else if get_text_width(font_bundle, buffer.clone() + " " + word) > max_width
+                    } else if text_width(&(buffer.clone() + " " + word)) > max_width
                         && !buffer.is_empty()
                     {
-                        if cfg!(debug_assertions) {
-                            println!("Word {} goes over max width && buffer is not empty.", word);
-                        }
+                        trace_log!("Word {} goes over max width && buffer is not empty.", word);
                         //write buffer to lines_altered, empty buffer, evaluate as new line
                         lines_altered.push(buffer);
                         buffer = String::new();
                         let word_chars = word.chars();
                         for word_char in word_chars {
-                            if get_text_width(font_bundle, buffer.clone() + "-") <= max_width {
+                            if text_width(&(buffer.clone() + "-")) <= max_width {
                                 buffer = buffer + &word_char.to_string();
                             } else {
                                 buffer += "-";
@@ -176,25 +1166,194 @@ pub fn text_on_image<T: AsRef<str>>(
                 }
                 lines_altered.push(buffer);
             }
-            let lines_altered: Vec<&str> = lines_altered.iter().map(|line| line.as_str()).collect();
-            if cfg!(debug_assertions) {
-                println!("Lines altered:\n{:?}", lines_altered);
-            }
-            position_and_draw(
-                image,
-                lines_altered,
-                font_bundle,
-                pixels_from_left,
-                pixels_from_top,
-                horizontal_justify,
-                vertical_anchor,
-            )
+            debug_log!("Lines altered:\n{:?}", lines_altered);
+            lines_altered
+        }
+    }
+}
+
+/// Wraps text with line breaks that minimize the sum of squared unused space at the end of each
+/// line, instead of filling each line as much as possible.
+///
+/// This is a simpler version of TeX's paragraph breaking. It is slower than the greedy wrapping
+/// of `WrapBehavior::Wrap`, but gives much more even lines for body text. Words wider than
+/// `max_width` are hyphenated the same way `WrapBehavior::Wrap` does.
+pub fn wrap_text_greedy_optimal<T: AsRef<str>>(
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    max_width: u32,
+) -> Vec<String> {
+    wrap_text_to_lines(text, font_bundle, &WrapBehavior::WrapOptimal(max_width))
+}
+
+/// Helper function to break a single paragraph for `WrapBehavior::WrapAtCharsPreferWords`.
+fn wrap_paragraph_at_chars(paragraph: &str, max_chars: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut remaining: Vec<char> = paragraph.chars().collect();
+    while remaining.len() > max_chars {
+        // A space right after the limit still lets the whole first max_chars fit.
+        let last_space = remaining[1..=max_chars]
+            .iter()
+            .rposition(|c| c.is_whitespace())
+            .map(|index| index + 1);
+        let (line, rest) = match last_space {
+            Some(space) => (&remaining[..space], &remaining[space + 1..]),
+            None => remaining.split_at(max_chars),
+        };
+        lines.push(line.iter().collect::<String>().trim_end().to_string());
+        remaining = rest
+            .iter()
+            .collect::<String>()
+            .trim_start()
+            .chars()
+            .collect();
+    }
+    lines.push(remaining.into_iter().collect());
+    lines
+}
+
+/// Helper function to break a single paragraph for `WrapBehavior::WrapStrictNoHyphen`.
+fn wrap_paragraph_at_words<F: Fn(&str) -> u32>(
+    paragraph: &str,
+    max_width: u32,
+    text_width: &F,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut buffer = String::new();
+    for word in paragraph.split_whitespace() {
+        if buffer.is_empty() {
+            buffer += word;
+        } else if text_width(&format!("{} {}", buffer, word)) <= max_width {
+            buffer = buffer + " " + word;
+        } else {
+            lines.push(std::mem::replace(&mut buffer, word.to_string()));
+        }
+    }
+    lines.push(buffer);
+    lines
+}
+
+/// Helper function to break a single paragraph for `WrapBehavior::WrapOptimal`.
+fn wrap_paragraph_optimal(
+    paragraph: &str,
+    max_width: u32,
+    text_width: &dyn Fn(&str) -> u32,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+    for word in paragraph.split_whitespace() {
+        if text_width(word) <= max_width {
+            words.push(word.to_string());
+            continue;
         }
+        // A word that cannot fit on any line is hyphenated on lines of its own, and its last
+        // piece starts the next run of words.
+        let mut pieces = wrap_lines_with(word, &WrapBehavior::Wrap(max_width), text_width);
+        let last_piece = pieces.pop().unwrap_or_default();
+        lines.extend(break_words_optimal(&words, max_width, text_width));
+        lines.extend(pieces);
+        words = vec![last_piece];
+    }
+    lines.extend(break_words_optimal(&words, max_width, text_width));
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+/// Helper function to choose the line breaks between words that each fit within `max_width`.
+fn break_words_optimal(
+    words: &[String],
+    max_width: u32,
+    text_width: &dyn Fn(&str) -> u32,
+) -> Vec<String> {
+    let word_count = words.len();
+    // best[i] is the lowest cost of laying out words[i..], and next[i] is where its first line ends.
+    let mut best = vec![u64::MAX; word_count + 1];
+    let mut next = vec![word_count; word_count + 1];
+    best[word_count] = 0;
+    for start in (0..word_count).rev() {
+        for end in start + 1..=word_count {
+            let line_width = text_width(&words[start..end].join(" "));
+            if line_width > max_width && end > start + 1 {
+                break;
+            }
+            let cost = if end == word_count {
+                0
+            } else {
+                let unused = max_width.saturating_sub(line_width) as u64;
+                unused * unused
+            };
+            let total = cost.saturating_add(best[end]);
+            if total < best[start] {
+                best[start] = total;
+                next[start] = end;
+            }
+        }
+    }
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while start < word_count {
+        lines.push(words[start..next[start]].join(" "));
+        start = next[start];
     }
+    lines
 }
 
-/// Helper function to get text width.
-fn get_text_width<T: AsRef<str>>(font_bundle: &FontBundle, text: T) -> u32 {
+/// Gets the total height of `line_count` lines, such as for sizing a new image to fit text.
+pub fn measure_text_height_for_lines(line_count: usize, font_bundle: &FontBundle<'_>) -> u32 {
+    line_count as u32 * get_text_height(font_bundle) as u32
+}
+
+/// Gets the total height of text after wrapping it, without needing an image.
+pub fn measure_wrapped_block_height<T: AsRef<str>>(
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    wrap_behavior: &WrapBehavior,
+) -> u32 {
+    let line_count = wrap_text_to_lines(text, font_bundle, wrap_behavior).len();
+    measure_text_height_for_lines(line_count, font_bundle)
+}
+
+/// Measures the area text would cover if drawn with the same arguments as [`text_on_image`].
+pub fn measure_wrapped_text<T: AsRef<str>>(
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement {
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    measure_lines(
+        &line_widths(font_bundle, &lines),
+        get_text_height(font_bundle),
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Loads a font from a TrueType or OpenType file.
+///
+/// Returns `TextOnImageError::FontNotFound` if there is no file at `path`.
+pub fn load_font<P: AsRef<Path>>(path: P) -> Result<Font<'static>, TextOnImageError> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => TextOnImageError::FontNotFound {
+            name: path.display().to_string(),
+        },
+        _ => TextOnImageError::IoError(err),
+    })?;
+    Font::try_from_vec(data).ok_or(TextOnImageError::InvalidFont)
+}
+
+/// Gets the width in pixels of text drawn as a single line, without wrapping.
+pub fn get_text_width<T: AsRef<str>>(font_bundle: &FontBundle, text: T) -> u32 {
     font_bundle
         .font
         .layout(text.as_ref(), font_bundle.scale, point(0., 0.))
@@ -203,6 +1362,407 @@ fn get_text_width<T: AsRef<str>>(font_bundle: &FontBundle, text: T) -> u32 {
         .unwrap_or(0.) as u32
 }
 
+/// Gets the horizontal offset of every character from the start of a single line.
+///
+/// The result has one more entry than the line has characters: character `i` spans from
+/// `positions[i]` to `positions[i + 1]`, and the last entry is the width of the line.
+pub fn get_char_positions<T: AsRef<str>>(font_bundle: &FontBundle<'_>, line: T) -> Vec<u32> {
+    let glyphs = layout_glyphs(
+        font_bundle,
+        line.as_ref(),
+        point(0., 0.),
+        &|_, _, kerning| kerning,
+    );
+    let mut positions: Vec<u32> = glyphs
+        .iter()
+        .map(|glyph| glyph.position().x as u32)
+        .collect();
+    positions.push(glyphs_width(&glyphs));
+    positions
+}
+
+/// Gets the exact rectangle of pixels [`text_on_image`] would paint, without drawing anything.
+///
+/// Unlike [`measure_wrapped_text`], which covers whole line boxes, this only covers the glyphs
+/// themselves. Text with nothing visible gives an empty box at the given coordinates.
+pub fn get_text_bounding_box<T: AsRef<str>>(
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextBoundingBox {
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let metrics = line_metrics(
+        &lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let mut bounding_box: Option<TextBoundingBox> = None;
+    for line_metrics in &metrics {
+        let is_last_line = line_metrics.index + 1 == lines.len();
+        let stretch_to = if horizontal_justify.is_full_justify() && !is_last_line {
+            Some(line_metrics.width)
+        } else {
+            None
+        };
+        for glyph in line_glyphs(font_bundle, line_metrics, stretch_to) {
+            if let Some(pixels) = glyph.pixel_bounding_box() {
+                bounding_box = Some(match bounding_box {
+                    None => TextBoundingBox {
+                        left: pixels.min.x,
+                        top: pixels.min.y,
+                        right: pixels.max.x,
+                        bottom: pixels.max.y,
+                    },
+                    Some(b) => TextBoundingBox {
+                        left: b.left.min(pixels.min.x),
+                        top: b.top.min(pixels.min.y),
+                        right: b.right.max(pixels.max.x),
+                        bottom: b.bottom.max(pixels.max.y),
+                    },
+                });
+            }
+        }
+    }
+    bounding_box.unwrap_or(TextBoundingBox {
+        left: pixels_from_left,
+        top: pixels_from_top,
+        right: pixels_from_left,
+        bottom: pixels_from_top,
+    })
+}
+
+/// Works out everything about how [`text_on_image`] would lay out text, without an image to draw
+/// on, for layout engines that render in a separate step.
+pub fn text_on_image_measure_only<T: AsRef<str>>(
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> FullTextLayout {
+    let text = text.as_ref();
+    let wrapped_text = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let line_refs: Vec<&str> = wrapped_text.iter().map(|line| line.as_str()).collect();
+    let lines = line_metrics(
+        &line_refs,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let measurement = measure_lines(
+        &line_widths(font_bundle, &line_refs),
+        get_text_height(font_bundle),
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let bounding_box = get_text_bounding_box(
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    FullTextLayout {
+        bounding_box,
+        lines,
+        wrapped_text,
+        total_width: measurement.width,
+        total_height: measurement.height,
+    }
+}
+
+/// Draws text on an image like [`text_on_image`], skipping any character that lies entirely
+/// outside the image and reporting what was cut off.
+///
+/// Useful when the position comes from user input, since the caller learns whether the text was
+/// truncated instead of it silently disappearing off the edge.
+pub fn text_on_image_safe<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> (TextMeasurement, ClipInfo) {
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let measurement = measure_lines(
+        &line_widths(font_bundle, &lines),
+        get_text_height(font_bundle),
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let image_box = TextBoundingBox {
+        left: 0,
+        top: 0,
+        right: image.width() as i32,
+        bottom: image.height() as i32,
+    };
+    let mut clip_info = ClipInfo::default();
+    let metrics = line_metrics(
+        &lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    for line_metrics in &metrics {
+        let is_last_line = line_metrics.index + 1 == lines.len();
+        let stretch_to = if horizontal_justify.is_full_justify() && !is_last_line {
+            Some(line_metrics.width)
+        } else {
+            None
+        };
+        let mut line_clipped = false;
+        for glyph in line_glyphs(font_bundle, line_metrics, stretch_to) {
+            let Some(pixels) = glyph.pixel_bounding_box() else {
+                continue;
+            };
+            let glyph_box = TextBoundingBox {
+                left: pixels.min.x,
+                top: pixels.min.y,
+                right: pixels.max.x,
+                bottom: pixels.max.y,
+            };
+            let is_inside = glyph_box.left >= image_box.left
+                && glyph_box.top >= image_box.top
+                && glyph_box.right <= image_box.right
+                && glyph_box.bottom <= image_box.bottom;
+            if !is_inside {
+                clip_info.chars_clipped += 1;
+                line_clipped = true;
+            }
+            if glyph_box.intersects(&image_box) {
+                draw_glyph(image, font_bundle.color, &glyph);
+            }
+        }
+        if line_clipped {
+            clip_info.lines_clipped += 1;
+        }
+    }
+    (measurement, clip_info)
+}
+
+/// Helper function to get the glyphs of a line exactly where they will be drawn.
+fn line_glyphs<'a>(
+    font_bundle: &FontBundle<'a>,
+    line_metrics: &LineMetrics,
+    stretch_to: Option<u32>,
+) -> Vec<PositionedGlyph<'a>> {
+    let kerning_only = |_, _, kerning| kerning;
+    let baseline = line_metrics.top as f32 + font_bundle.font.v_metrics(font_bundle.scale).ascent;
+    match stretch_to {
+        None => layout_glyphs(
+            font_bundle,
+            &line_metrics.text,
+            point(line_metrics.left as f32, baseline),
+            &kerning_only,
+        ),
+        Some(block_width) => {
+            let words: Vec<&str> = line_metrics.text.split_whitespace().collect();
+            let gap = justified_gap(font_bundle, &words, block_width);
+            let mut word_x = line_metrics.left as f32;
+            let mut glyphs = Vec::new();
+            for word in words {
+                glyphs.extend(layout_glyphs(
+                    font_bundle,
+                    word,
+                    point(word_x.round(), baseline),
+                    &kerning_only,
+                ));
+                word_x += get_text_width(font_bundle, word) as f32 + gap;
+            }
+            glyphs
+        }
+    }
+}
+
+/// Helper function to get the glyphs of wrapped lines exactly where they will be drawn, in
+/// reading order.
+fn placed_glyphs<'a>(
+    lines: &[&str],
+    font_bundle: &FontBundle<'a>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> Vec<GlyphRenderInfo<'a>> {
+    let metrics = line_metrics(
+        lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let mut placed = Vec::new();
+    for line_metrics in &metrics {
+        let is_last_line = line_metrics.index + 1 == lines.len();
+        let stretch_to = if horizontal_justify.is_full_justify() && !is_last_line {
+            Some(line_metrics.width)
+        } else {
+            None
+        };
+        // Stretched lines are laid out word by word, leaving out the whitespace between them.
+        let characters = line_metrics
+            .text
+            .chars()
+            .enumerate()
+            .filter(|&(_, character)| stretch_to.is_none() || !character.is_whitespace());
+        let glyphs = line_glyphs(font_bundle, line_metrics, stretch_to);
+        for ((char_index, character), glyph) in characters.zip(glyphs) {
+            let position = glyph.position();
+            placed.push(GlyphRenderInfo {
+                char: character,
+                line_index: line_metrics.index,
+                char_index,
+                draw_x: position.x.round() as i32,
+                draw_y: position.y.round() as i32,
+                glyph,
+            });
+        }
+    }
+    placed
+}
+
+/// Helper function to get where every character of each wrapped line starts as drawn, like
+/// [`get_char_positions`] but in image coordinates and stretched for fully justified lines.
+///
+/// Each line's edges end with where its last character ends. Whitespace left out of a stretched
+/// line starts where the character before it ends.
+fn drawn_char_edges(
+    lines: &[&str],
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> Vec<(LineMetrics, Vec<i32>)> {
+    let metrics = line_metrics(
+        lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let mut placed = placed_glyphs(
+        lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+    .into_iter()
+    .peekable();
+    metrics
+        .into_iter()
+        .map(|line_metrics| {
+            let left = line_metrics.left as f32;
+            let mut pen_x = left;
+            let mut edges = Vec::new();
+            for char_index in 0..line_metrics.text.chars().count() {
+                let glyph = placed.next_if(|placed| {
+                    placed.line_index == line_metrics.index && placed.char_index == char_index
+                });
+                if let Some(placed) = glyph {
+                    pen_x = placed.glyph.position().x;
+                    edges.push(line_metrics.left + (pen_x - left) as i32);
+                    pen_x += placed.glyph.unpositioned().h_metrics().advance_width;
+                } else {
+                    edges.push(line_metrics.left + (pen_x - left) as i32);
+                }
+            }
+            edges.push(line_metrics.left + (pen_x - left) as i32);
+            (line_metrics, edges)
+        })
+        .collect()
+}
+
+/// Helper function to find which character of `text` each character of its wrapped lines came
+/// from, as an index into `text.chars()`, or `None` for a hyphen added at a break.
+///
+/// Wrapping drops whitespace and adds hyphens, so each line character is matched to the next
+/// character of `text` it equals past any whitespace.
+fn wrapped_char_sources(text: &str, lines: &[&str]) -> Vec<Vec<Option<usize>>> {
+    let text: Vec<char> = text.chars().collect();
+    let mut next = 0;
+    lines
+        .iter()
+        .map(|line| {
+            line.chars()
+                .map(|character| {
+                    let skipped = text[next..]
+                        .iter()
+                        .take_while(|&&source| source != character && source.is_whitespace())
+                        .count();
+                    let found = if text.get(next + skipped) == Some(&character) {
+                        Some(next + skipped)
+                    } else if character == '-' {
+                        None
+                    } else {
+                        // Anything else wrapping left out is skipped over too.
+                        text[next..]
+                            .iter()
+                            .position(|&source| source == character)
+                            .map(|offset| next + offset)
+                    };
+                    if let Some(source) = found {
+                        next = source + 1;
+                    }
+                    found
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Gets the characters in the text that the font has no glyph for, in the order they first appear.
+///
+/// rusttype silently skips these characters instead of drawing a placeholder. Control characters
+/// such as newlines are never reported.
+pub fn check_font_has_glyphs<T: AsRef<str>>(font: &Font<'_>, text: T) -> Vec<char> {
+    let mut missing = Vec::new();
+    for c in text.as_ref().chars() {
+        if !c.is_control() && font.glyph(c).id() == GlyphId(0) && !missing.contains(&c) {
+            missing.push(c);
+        }
+    }
+    missing
+}
+
 /// Helper function to get text height.
 fn get_text_height(font_bundle: &FontBundle) -> i32 {
     let v_metrics = font_bundle.font.v_metrics(font_bundle.scale);
@@ -238,50 +1798,4236 @@ pub fn text_on_image_draw_debug<T: AsRef<str>>(
     );
 }
 
-fn position_and_draw(
+/// Draws text on an image with a small cross where the coordinates are, like
+/// [`text_on_image_draw_debug`], and returns where everything ended up.
+pub fn text_on_image_draw_debug_v2<T: AsRef<str>>(
     image: &mut DynamicImage,
-    lines: Vec<&str>,
+    text: T,
     font_bundle: &FontBundle<'_>,
     pixels_from_left: i32,
     pixels_from_top: i32,
     horizontal_justify: TextJustify,
     vertical_anchor: VerticalAnchor,
-) {
-    let lines_len = lines.len() as i32;
-    let mut current_line = 0;
-    for &line in &lines {
-        if cfg!(debug_assertions) {
-            println!("{} width: {}", line, get_text_width(font_bundle, line));
-        }
-        let vertical_offset = match vertical_anchor {
-            VerticalAnchor::Top => get_text_height(font_bundle) * current_line,
-            VerticalAnchor::Center => {
-                (get_text_height(font_bundle) * current_line
-                    - get_text_height(font_bundle) * (lines_len - current_line))
-                    / 2
-            }
-            VerticalAnchor::Bottom => -(get_text_height(font_bundle) * (lines_len - current_line)),
-        };
-        let horizontal_offset = match horizontal_justify {
-            TextJustify::Left => 0,
-            TextJustify::Center => get_text_width(font_bundle, line) / 2,
-            TextJustify::Right => get_text_width(font_bundle, line),
-        };
-        draw_text_mut(
-            image,
-            font_bundle.color,
-            pixels_from_left - horizontal_offset as i32,
-            pixels_from_top + vertical_offset,
-            font_bundle.scale,
-            font_bundle.font,
-            line,
-        );
-        if cfg!(debug_assertions) {
-            println!("pixels_from_left for line {}: {}", line, pixels_from_left);
-        }
-        current_line += 1;
+    wrap_behavior: WrapBehavior,
+) -> DebugInfo {
+    let text = text.as_ref();
+    let wrapped_lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let line_refs: Vec<&str> = wrapped_lines.iter().map(|line| line.as_str()).collect();
+    let lines = line_metrics(
+        &line_refs,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let bounding_box = get_text_bounding_box(
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    text_on_image_draw_debug(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    DebugInfo {
+        anchor: (pixels_from_left, pixels_from_top),
+        bounding_box,
+        lines,
+        wrapped_lines,
     }
 }
 
+/// Draws a single line of text in the bottom right corner of an image, such as a watermark or
+/// copyright notice.
+///
+/// `margin` is the spacing from both the right and bottom edges.
+pub fn text_on_image_align_bottom_right<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    margin: u32,
+) -> TextMeasurement {
+    let pixels_from_left = image.width() as i32 - margin as i32;
+    let pixels_from_top = image.height() as i32 - margin as i32;
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        TextJustify::Right,
+        VerticalAnchor::Bottom,
+        WrapBehavior::NoWrap,
+    )
+}
+
+/// Draws a label made of a highlighted prefix followed immediately by a suffix, like
+/// "75% complete".
+///
+/// The prefix starts at `pixels_from_left` and the suffix starts where the prefix ends.
+pub fn text_on_image_progress_label(
+    image: &mut DynamicImage,
+    prefix: &str,
+    suffix: &str,
+    prefix_font_bundle: &FontBundle<'_>,
+    suffix_font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    vertical_anchor: VerticalAnchor,
+) -> (TextMeasurement, TextMeasurement) {
+    let prefix_measurement = text_on_image(
+        image,
+        prefix,
+        prefix_font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        TextJustify::Left,
+        vertical_anchor,
+        WrapBehavior::NoWrap,
+    );
+    let suffix_measurement = text_on_image(
+        image,
+        suffix,
+        suffix_font_bundle,
+        pixels_from_left + get_text_width(prefix_font_bundle, prefix) as i32,
+        pixels_from_top,
+        TextJustify::Left,
+        vertical_anchor,
+        WrapBehavior::NoWrap,
+    );
+    (prefix_measurement, suffix_measurement)
+}
+
+/// Draws text flowing from one column to the next.
+///
+/// Text is wrapped to `column_width` and a column takes as many lines as fit in `max_height`
+/// before the rest move on to the next column. Justification and vertical anchor are applied
+/// within each column's box.
+pub fn text_on_image_columns<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    config: ColumnConfig,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+) -> ColumnLayout {
+    let lines = wrap_text_to_lines(text, font_bundle, &WrapBehavior::Wrap(config.column_width));
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let lines_per_column =
+        (config.max_height / get_text_height(font_bundle).max(1) as u32) as usize;
+    let mut columns_used = 0;
+    let mut lines_drawn = 0;
+    if lines_per_column > 0 {
+        for column_lines in lines
+            .chunks(lines_per_column)
+            .take(config.num_columns as usize)
+        {
+            let column_left =
+                config.origin_x + (columns_used * (config.column_width + config.column_gap)) as i32;
+            let pixels_from_left = column_left
+                + match horizontal_justify {
+                    TextJustify::Left
+                    | TextJustify::JustifyLastLeft
+                    | TextJustify::JustifyLastRight
+                    | TextJustify::JustifyLastCenter => 0,
+                    TextJustify::Center => config.column_width as i32 / 2,
+                    TextJustify::Right => config.column_width as i32,
+                };
+            let pixels_from_top = config.origin_y
+                + match vertical_anchor {
+                    VerticalAnchor::Top => 0,
+                    VerticalAnchor::Center => config.max_height as i32 / 2,
+                    VerticalAnchor::Bottom => config.max_height as i32,
+                };
+            position_and_draw(
+                image,
+                column_lines.to_vec(),
+                font_bundle,
+                pixels_from_left,
+                pixels_from_top,
+                horizontal_justify,
+                vertical_anchor,
+                WrapBehavior::Wrap(config.column_width),
+            );
+            columns_used += 1;
+            lines_drawn += column_lines.len();
+        }
+    }
+    let text_truncated = lines_drawn < lines.len();
+    ColumnLayout {
+        columns_used,
+        text_truncated,
+        remaining_text: if text_truncated {
+            Some(lines[lines_drawn..].join("\n"))
+        } else {
+            None
+        },
+    }
+}
+
+/// Draws text positioned relative to the measurement of previously drawn text.
+pub fn text_on_image_align_to<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    reference: &TextMeasurement,
+    alignment: RelativeAlignment,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement {
+    let right = reference.left + reference.width as i32;
+    let bottom = reference.top + reference.height as i32;
+    let (pixels_from_left, pixels_from_top, horizontal_justify, vertical_anchor) = match alignment {
+        RelativeAlignment::RightOf { gap } => (
+            right + gap as i32,
+            reference.top,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+        ),
+        RelativeAlignment::Below { gap } => (
+            reference.left,
+            bottom + gap as i32,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+        ),
+        RelativeAlignment::LeftOf { gap } => (
+            reference.left - gap as i32,
+            reference.top,
+            TextJustify::Right,
+            VerticalAnchor::Top,
+        ),
+        RelativeAlignment::Above { gap } => (
+            reference.left,
+            reference.top - gap as i32,
+            TextJustify::Left,
+            VerticalAnchor::Bottom,
+        ),
+        RelativeAlignment::CenterAlignedWith => (
+            reference.left + reference.width as i32 / 2,
+            reference.top + reference.height as i32 / 2,
+            TextJustify::Center,
+            VerticalAnchor::Center,
+        ),
+    };
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text with extra space between every character, like CSS `letter-spacing` in ems.
+///
+/// `tracking_em = 0.05` is the same as `letter-spacing: 0.05em`, and negative values tighten
+/// the text. Wrapping uses the tracked widths.
+pub fn text_on_image_with_tracking<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    tracking_em: f32,
+) -> TextMeasurement {
+    let tracking_pixels = tracking_em * font_bundle.scale.x;
+    text_on_image_spaced(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+        |_, _, kerning| kerning + tracking_pixels,
+    )
+}
+
+/// Draws text with every space widened by `extra_space_px`, like CSS `word-spacing`.
+///
+/// Negative values narrow the gaps between words. Unlike [`text_on_image_with_tracking`], the
+/// space between letters is left alone. Wrapping uses the adjusted widths.
+pub fn text_on_image_with_word_spacing<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    extra_space_px: f32,
+) -> TextMeasurement {
+    text_on_image_spaced(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+        |previous, _, kerning| {
+            if previous == ' ' {
+                kerning + extra_space_px
+            } else {
+                kerning
+            }
+        },
+    )
+}
+
+/// Draws text with a highlight behind every match of a regular expression.
+///
+/// Matches are found within each drawn line, so a match cannot span a wrapped line break.
+/// Overlapping or touching matches share one highlight, and `highlight_color`'s alpha is
+/// blended over the image before the text is drawn on top.
+#[cfg(feature = "regex")]
+pub fn text_on_image_highlight_matches<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    pattern: &regex::Regex,
+    highlight_color: Rgba<u8>,
+) -> TextMeasurement {
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let line_height = get_text_height(font_bundle);
+    let line_edges = drawn_char_edges(
+        &lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    for (line_metrics, edges) in &line_edges {
+        let line = line_metrics.text.as_str();
+        let char_index = |byte_index: usize| line[..byte_index].chars().count();
+        let mut spans: Vec<(i32, i32)> = pattern
+            .find_iter(line)
+            .filter(|found| !found.is_empty())
+            .map(|found| {
+                (
+                    edges[char_index(found.start())],
+                    edges[char_index(found.end())],
+                )
+            })
+            .collect();
+        spans.sort_unstable();
+        let mut merged: Vec<(i32, i32)> = Vec::new();
+        for (start, end) in spans {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        for (start, end) in merged {
+            blend_filled_rect(
+                image,
+                start,
+                line_metrics.top,
+                (end - start) as u32,
+                line_height as u32,
+                highlight_color,
+            );
+        }
+    }
+    let measurement = measure_lines(
+        &line_widths(font_bundle, &lines),
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    position_and_draw(
+        image,
+        lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    measurement
+}
+
+/// Draws text with any combination of decorations, skipping the ones that are `None`.
+///
+/// Decorations are drawn in the order background, shadow, outline, text, underline.
+pub fn text_on_image_configured<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    shadow: Option<ShadowConfig>,
+    outline: Option<OutlineConfig>,
+    background: Option<BackgroundConfig>,
+    underline: Option<UnderlineConfig>,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let widths = line_widths(font_bundle, &lines);
+    let line_height = get_text_height(font_bundle);
+    let measurement = measure_lines(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let draw_copy = |image: &mut DynamicImage, color: Rgba<u8>, offset_x: i32, offset_y: i32| {
+        let mut font_bundle = *font_bundle;
+        font_bundle.set_color(color);
+        position_and_draw(
+            image,
+            lines.clone(),
+            &font_bundle,
+            pixels_from_left + offset_x,
+            pixels_from_top + offset_y,
+            horizontal_justify,
+            vertical_anchor,
+            wrap_behavior,
+        );
+    };
+    if let Some(background) = background {
+        blend_filled_rect(
+            image,
+            measurement.left - background.padding as i32,
+            measurement.top - background.padding as i32,
+            measurement.width + 2 * background.padding,
+            measurement.height + 2 * background.padding,
+            background.color,
+        );
+    }
+    if let Some(shadow) = shadow {
+        draw_shadow(
+            image,
+            text,
+            font_bundle,
+            pixels_from_left,
+            pixels_from_top,
+            horizontal_justify,
+            vertical_anchor,
+            wrap_behavior,
+            shadow,
+        );
+    }
+    if let Some(outline) = outline {
+        for (offset_x, offset_y) in outline_offsets(outline.width) {
+            draw_copy(image, outline.color, offset_x, offset_y);
+        }
+    }
+    draw_copy(image, font_bundle.color, 0, 0);
+    if let Some(underline) = underline {
+        let ascent = font_bundle.font.v_metrics(font_bundle.scale).ascent as i32;
+        let positions = line_positions(
+            &widths,
+            line_height,
+            pixels_from_left,
+            pixels_from_top,
+            horizontal_justify,
+            vertical_anchor,
+            wrap_behavior,
+        );
+        let block_width = justify_width(&widths, wrap_behavior);
+        for (index, (&line_width, &(line_x, line_y))) in widths.iter().zip(&positions).enumerate() {
+            // Stretched lines reach the block width, except single words, which stay put.
+            let is_stretched = horizontal_justify.is_full_justify()
+                && index + 1 != lines.len()
+                && lines[index].split_whitespace().nth(1).is_some();
+            blend_filled_rect(
+                image,
+                line_x,
+                line_y + ascent + underline.offset,
+                if is_stretched {
+                    block_width
+                } else {
+                    line_width
+                },
+                underline.thickness,
+                underline.color,
+            );
+        }
+    }
+    measurement
+}
+
+/// Draws outlined text over a background rectangle, with the rectangle grown by the outline's
+/// width so `background.padding` is measured from the outermost outline pixel.
+///
+/// Returns the area of the background rectangle, outline and padding included, with the text's
+/// line count.
+pub fn text_on_image_with_outline_and_background<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    outline: OutlineConfig,
+    background: BackgroundConfig,
+) -> TextMeasurement {
+    let reach = outline.width + background.padding;
+    let measurement = text_on_image_configured(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+        None,
+        Some(outline),
+        Some(BackgroundConfig {
+            padding: reach,
+            ..background
+        }),
+        None,
+    );
+    TextMeasurement {
+        left: measurement.left - reach as i32,
+        top: measurement.top - reach as i32,
+        width: measurement.width + 2 * reach,
+        height: measurement.height + 2 * reach,
+        ..measurement
+    }
+}
+
+/// Draws text inside two outlines of different colors, like sports and tournament titles.
+pub fn text_on_image_with_double_outline<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    outline: DoubleOutlineConfig,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let outer = OutlineConfig {
+        width: outline.inner.width + outline.outer.width,
+        color: outline.outer.color,
+    };
+    // The outer outline goes down first so the inner one covers its middle.
+    for OutlineConfig { width, color } in [outer, outline.inner] {
+        let mut font_bundle = *font_bundle;
+        font_bundle.set_color(color);
+        for (offset_x, offset_y) in outline_offsets(width) {
+            text_on_image(
+                image,
+                text,
+                &font_bundle,
+                pixels_from_left + offset_x,
+                pixels_from_top + offset_y,
+                horizontal_justify,
+                vertical_anchor,
+                wrap_behavior,
+            );
+        }
+    }
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text over a stack of shadows.
+///
+/// `shadows` are listed nearest first and drawn in reverse, so the last is at the bottom of the
+/// stack and the first sits just under the text, which is drawn on top of them all.
+pub fn text_on_image_with_shadow_multiple<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    shadows: &[ShadowConfig],
+) -> TextMeasurement {
+    let text = text.as_ref();
+    for &shadow in shadows.iter().rev() {
+        draw_shadow(
+            image,
+            text,
+            font_bundle,
+            pixels_from_left,
+            pixels_from_top,
+            horizontal_justify,
+            vertical_anchor,
+            wrap_behavior,
+            shadow,
+        );
+    }
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text over a shadow placed by direction and distance.
+pub fn text_on_image_with_shadow_angle<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    shadow: AngleShadowConfig,
+) -> TextMeasurement {
+    text_on_image_with_shadow_multiple(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+        &[shadow.into()],
+    )
+}
+
+/// Draws text over a sharp shadow in a darker shade of the text's own color.
+///
+/// `shadow_darkness` runs from 0.0, a shadow the same color as the text, to 1.0, a black
+/// shadow, and is clamped to that range. The shadow keeps the text color's alpha.
+pub fn text_on_image_with_auto_shadow<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    shadow_offset: (i32, i32),
+    shadow_darkness: f32,
+) -> TextMeasurement {
+    let brightness = 1. - shadow_darkness.clamp(0., 1.);
+    let mut color = font_bundle.color();
+    for channel in &mut color.0[..3] {
+        *channel = (*channel as f32 * brightness) as u8;
+    }
+    text_on_image_with_shadow_multiple(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+        &[ShadowConfig {
+            offset_x: shadow_offset.0,
+            offset_y: shadow_offset.1,
+            color,
+            blur_radius: None,
+        }],
+    )
+}
+
+/// Draws text over a long shadow, `config.length` copies of the text each one pixel further in
+/// the direction of `config.angle_deg`.
+///
+/// With `config.fade`, each copy is more transparent than the one before, down to nearly clear
+/// at the far end.
+pub fn text_on_image_with_long_shadow<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    config: LongShadowConfig,
+) -> TextMeasurement {
+    let shadows: Vec<ShadowConfig> = (1..=config.length)
+        .map(|step| {
+            let mut color = config.color;
+            if config.fade {
+                let remaining = (config.length - step + 1) as f32 / config.length as f32;
+                color[3] = (color[3] as f32 * remaining).round() as u8;
+            }
+            ShadowConfig::from_angle(config.angle_deg, step as f32, color)
+        })
+        .collect();
+    text_on_image_with_shadow_multiple(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+        &shadows,
+    )
+}
+
+/// Helper function to draw one shadow of the text, blurring it if the shadow asks for it.
+fn draw_shadow(
+    image: &mut DynamicImage,
+    text: &str,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    shadow: ShadowConfig,
+) {
+    let shadow_left = pixels_from_left + shadow.offset_x;
+    let shadow_top = pixels_from_top + shadow.offset_y;
+    match shadow.blur_radius {
+        Some(blur_radius) if blur_radius > 0. => {
+            let (mask, _) = render_text_to_mask(
+                image.width(),
+                image.height(),
+                text,
+                font_bundle,
+                shadow_left,
+                shadow_top,
+                horizontal_justify,
+                vertical_anchor,
+                wrap_behavior,
+            );
+            let mask = gaussian_blur_f32(&mask, blur_radius);
+            effects::composite_mask(image, &mask, shadow.color, BlendMode::Normal);
+        }
+        _ => {
+            let mut font_bundle = *font_bundle;
+            font_bundle.set_color(shadow.color);
+            text_on_image(
+                image,
+                text,
+                &font_bundle,
+                shadow_left,
+                shadow_top,
+                horizontal_justify,
+                vertical_anchor,
+                wrap_behavior,
+            );
+        }
+    }
+}
+
+/// Helper function to get every offset within `width` pixels of the origin, except the origin.
+fn outline_offsets(width: u32) -> Vec<(i32, i32)> {
+    let width = width as i32;
+    let mut offsets = Vec::new();
+    for offset_y in -width..=width {
+        for offset_x in -width..=width {
+            if (offset_x, offset_y) != (0, 0)
+                && offset_x * offset_x + offset_y * offset_y <= width * width
+            {
+                offsets.push((offset_x, offset_y));
+            }
+        }
+    }
+    offsets
+}
+
+/// Draws text wrapped to `max_width` at the largest scale up to `max_scale` that fits within
+/// `max_width` by `max_height`.
+///
+/// The scale is reduced by 10% at a time and never goes below 6 pixels, so text that does not fit
+/// even then is drawn at 6 pixels anyway. Returns the font bundle that was used.
+pub fn text_on_image_with_max_font_size<'a, T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font: &'a Font<'a>,
+    color: Rgba<u8>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    max_scale: f32,
+    max_width: u32,
+    max_height: u32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+) -> (FontBundle<'a>, TextMeasurement) {
+    const MIN_SCALE: f32 = 6.;
+    let mut scale = max_scale.max(MIN_SCALE);
+    let (font_bundle, wrap_behavior) = loop {
+        let font_bundle = FontBundle::new(font, Scale::uniform(scale), color);
+        // Wrapping panics below 2 ems, so a max_width that narrow can never fit.
+        let can_wrap = get_text_width(&font_bundle, "mm") <= max_width;
+        if can_wrap {
+            let measurement = measure_wrapped_text(
+                text.as_ref(),
+                &font_bundle,
+                pixels_from_left,
+                pixels_from_top,
+                horizontal_justify,
+                vertical_anchor,
+                WrapBehavior::Wrap(max_width),
+            );
+            if measurement.width <= max_width && measurement.height <= max_height {
+                break (font_bundle, WrapBehavior::Wrap(max_width));
+            }
+        }
+        if scale <= MIN_SCALE {
+            let wrap_behavior = if can_wrap {
+                WrapBehavior::Wrap(max_width)
+            } else {
+                WrapBehavior::NoWrap
+            };
+            break (font_bundle, wrap_behavior);
+        }
+        scale = (scale * 0.9).max(MIN_SCALE);
+    };
+    let measurement = text_on_image(
+        image,
+        text,
+        &font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    (font_bundle, measurement)
+}
+
+/// Draws text on a single line, shrinking the font one pixel at a time from `starting_scale`
+/// until it is no wider than `max_width`. Returns the font bundle that was used.
+///
+/// The text is left justified at `pixels_from_left` and never wraps. Returns
+/// `TextOnImageError::CannotFitText` without drawing anything if the text is still too wide
+/// at `min_scale`.
+pub fn text_on_image_shrink_font_to_single_line<'a, T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font: &'a Font<'a>,
+    color: Rgba<u8>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    max_width: u32,
+    starting_scale: f32,
+    min_scale: f32,
+    vertical_anchor: VerticalAnchor,
+) -> Result<FontBundle<'a>, TextOnImageError> {
+    if min_scale <= 0. {
+        panic!("text_on_image: min_scale must be greater than zero!");
+    }
+    let mut scale = starting_scale;
+    while scale >= min_scale {
+        let font_bundle = FontBundle::new(font, Scale::uniform(scale), color);
+        if get_text_width(&font_bundle, text.as_ref()) <= max_width {
+            text_on_image(
+                image,
+                text,
+                &font_bundle,
+                pixels_from_left,
+                pixels_from_top,
+                TextJustify::Left,
+                vertical_anchor,
+                WrapBehavior::NoWrap,
+            );
+            return Ok(font_bundle);
+        }
+        // Try min_scale itself before giving up, even if the steps skip past it.
+        scale = if scale > min_scale {
+            (scale - 1.).max(min_scale)
+        } else {
+            break;
+        };
+    }
+    Err(TextOnImageError::CannotFitText)
+}
+
+/// Draws a block of text centered vertically on `pixels_from_top`, with adjustable spacing
+/// between lines.
+///
+/// A `line_spacing_ratio` of 1.0 uses the font's normal line height and 1.5 adds 50% more.
+pub fn text_on_image_vertically_centered_lines<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    wrap_behavior: WrapBehavior,
+    line_spacing_ratio: f32,
+) -> TextMeasurement {
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let widths = line_widths(font_bundle, &lines);
+    let line_height = (get_text_height(font_bundle) as f32 * line_spacing_ratio).round() as i32;
+    let positions = line_positions(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        VerticalAnchor::Center,
+        wrap_behavior,
+    );
+    draw_lines(
+        image,
+        &lines,
+        font_bundle,
+        &positions,
+        horizontal_justify,
+        justify_width(&widths, wrap_behavior),
+    );
+    measure_lines(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        VerticalAnchor::Center,
+        wrap_behavior,
+    )
+}
+
+/// Draws text like [`text_on_image`], calling `on_line` after each line is drawn.
+///
+/// The callback gets the line's metrics and the image, so it can add its own decorations.
+pub fn text_on_image_with_line_callback<T: AsRef<str>, F: FnMut(LineMetrics, &mut DynamicImage)>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    mut on_line: F,
+) -> TextMeasurement {
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let metrics = line_metrics(
+        &lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    for line_metrics in metrics {
+        let is_last_line = line_metrics.index + 1 == lines.len();
+        let stretch_to = if horizontal_justify.is_full_justify() && !is_last_line {
+            Some(line_metrics.width)
+        } else {
+            None
+        };
+        draw_line(
+            image,
+            &line_metrics.text,
+            font_bundle,
+            line_metrics.left,
+            line_metrics.top,
+            stretch_to,
+        );
+        on_line(line_metrics, image);
+    }
+    measure_lines(
+        &line_widths(font_bundle, &lines),
+        get_text_height(font_bundle),
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Lays out text like [`text_on_image`], but calls `on_glyph` for every character instead of
+/// drawing it, so the caller can draw each glyph however they want.
+///
+/// Glyphs come in reading order. Whitespace gets a call too, except between the words of fully
+/// justified lines, and its glyph has no pixels to draw. Use `glyph.pixel_bounding_box()` and
+/// `glyph.draw()` from rusttype to get at the glyph's coverage.
+pub fn text_on_image_with_glyph_callback<T, F>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    mut on_glyph: F,
+) -> TextMeasurement
+where
+    T: AsRef<str>,
+    F: FnMut(&mut DynamicImage, GlyphRenderInfo<'_>),
+{
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    for placed in placed_glyphs(
+        &lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    ) {
+        on_glyph(image, placed);
+    }
+    measure_lines(
+        &line_widths(font_bundle, &lines),
+        get_text_height(font_bundle),
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text at the position closest to the preferred one where it does not overlap any of the
+/// `occupied` boxes, such as chart labels that must not cover each other.
+///
+/// Candidate positions are tried on circles of growing radius around the preferred position, out
+/// to `search_radius` pixels. Returns `None` without drawing if every candidate overlaps.
+pub fn text_on_image_avoid_overlap<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    preferred_x: i32,
+    preferred_y: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    occupied: &[TextBoundingBox],
+    search_radius: u32,
+) -> Option<TextMeasurement> {
+    const STEP: u32 = 2;
+    let preferred_box = get_text_bounding_box(
+        text.as_ref(),
+        font_bundle,
+        preferred_x,
+        preferred_y,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let is_free = |offset_x: i32, offset_y: i32| {
+        let candidate = TextBoundingBox {
+            left: preferred_box.left + offset_x,
+            top: preferred_box.top + offset_y,
+            right: preferred_box.right + offset_x,
+            bottom: preferred_box.bottom + offset_y,
+        };
+        !occupied.iter().any(|other| candidate.intersects(other))
+    };
+    let mut found = None;
+    'search: for radius in (0..=search_radius).step_by(STEP as usize) {
+        let samples = if radius == 0 {
+            1
+        } else {
+            ((2. * std::f32::consts::PI * radius as f32) / STEP as f32).ceil() as u32
+        };
+        for sample in 0..samples {
+            let angle = 2. * std::f32::consts::PI * sample as f32 / samples as f32;
+            let offset_x = (radius as f32 * angle.cos()).round() as i32;
+            let offset_y = (radius as f32 * angle.sin()).round() as i32;
+            if is_free(offset_x, offset_y) {
+                found = Some((offset_x, offset_y));
+                break 'search;
+            }
+        }
+    }
+    let (offset_x, offset_y) = found?;
+    Some(text_on_image(
+        image,
+        text,
+        font_bundle,
+        preferred_x + offset_x,
+        preferred_y + offset_y,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    ))
+}
+
+/// Renders each animation frame as a copy of `base_image` with that frame's text drawn on it.
+pub fn text_on_image_animated_frames(
+    base_image: &DynamicImage,
+    frames: &[AnimationFrame],
+    font_bundle: &FontBundle<'_>,
+    wrap_behavior: WrapBehavior,
+) -> Vec<FramedImage> {
+    frames
+        .iter()
+        .map(|frame| {
+            let mut image = base_image.clone();
+            text_on_image(
+                &mut image,
+                &frame.text,
+                font_bundle,
+                frame.x,
+                frame.y,
+                frame.justify,
+                frame.anchor,
+                wrap_behavior,
+            );
+            FramedImage {
+                image,
+                delay_ms: frame.delay_ms,
+            }
+        })
+        .collect()
+}
+
+/// Renders frames of text scrolling across a copy of `base_image`, like a marquee.
+///
+/// The text starts just outside the edge it enters from and moves
+/// `config.scroll_speed_px_per_frame` pixels each frame. Horizontally scrolling text is centered
+/// vertically and vertically scrolling text is centered horizontally.
+///
+/// Every frame is a full copy of the base image, so memory grows with both the image size and
+/// `config.frame_count`. Long text scrolled slowly needs many frames to cross the image, so
+/// consider rendering and encoding frames in batches for long marquees.
+pub fn text_on_image_marquee<T: AsRef<str>>(
+    base_image: &DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    config: MarqueeConfig,
+) -> Vec<DynamicImage> {
+    let text = text.as_ref();
+    let block = measure_wrapped_text(
+        text,
+        font_bundle,
+        0,
+        0,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    let (image_width, image_height) = (base_image.width() as i32, base_image.height() as i32);
+    let (text_width, text_height) = (block.width as i32, block.height as i32);
+    let mut background = base_image.clone();
+    blend_filled_rect(
+        &mut background,
+        0,
+        0,
+        base_image.width(),
+        base_image.height(),
+        config.background,
+    );
+    (0..config.frame_count)
+        .map(|frame| {
+            let travelled = frame as i32 * config.scroll_speed_px_per_frame;
+            let centered_x = (image_width - text_width) / 2;
+            let centered_y = (image_height - text_height) / 2;
+            let (left, top) = match config.direction {
+                ScrollDirection::LeftToRight => (-text_width + travelled, centered_y),
+                ScrollDirection::RightToLeft => (image_width - travelled, centered_y),
+                ScrollDirection::TopToBottom => (centered_x, -text_height + travelled),
+                ScrollDirection::BottomToTop => (centered_x, image_height - travelled),
+            };
+            text_on_image_immutable(
+                &background,
+                text,
+                font_bundle,
+                left,
+                top,
+                TextJustify::Left,
+                VerticalAnchor::Top,
+                WrapBehavior::NoWrap,
+            )
+        })
+        .collect()
+}
+
+/// Renders frames of text appearing one character at a time on copies of `base_image`, timed by
+/// `ease`.
+///
+/// Frame `i` shows the first `ease(i / (frame_count - 1))` of the characters in reading order, so
+/// the first frame shows none and the last frame shows them all. A single frame shows the whole
+/// text. Text is laid out once for the whole string, so characters never move as more of them
+/// appear. Whitespace is counted like any other character, except between the words of fully
+/// justified lines.
+pub fn text_on_image_animated_reveal<T: AsRef<str>>(
+    base_image: &DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    frame_count: usize,
+    ease: EaseFunction,
+) -> Vec<DynamicImage> {
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let glyphs = placed_glyphs(
+        &lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    (0..frame_count)
+        .map(|frame| {
+            let progress = if frame_count == 1 {
+                1.
+            } else {
+                ease.ease(frame as f32 / (frame_count - 1) as f32)
+            };
+            let revealed = ((progress * glyphs.len() as f32).round() as usize).min(glyphs.len());
+            let mut image = base_image.clone();
+            for placed in &glyphs[..revealed] {
+                draw_glyph(&mut image, font_bundle.color, &placed.glyph);
+            }
+            image
+        })
+        .collect()
+}
+
+/// Draws text using the kerning from `kerning` for any pair it lists, and the font's kerning for
+/// every other pair.
+pub fn text_on_image_with_kerning<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    kerning: &KerningOverrides,
+) -> TextMeasurement {
+    text_on_image_spaced(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+        |previous, current, font_kerning| {
+            kerning
+                .pairs
+                .get(&(previous, current))
+                .copied()
+                .unwrap_or(font_kerning)
+        },
+    )
+}
+
+/// Draws lines that each have their own font bundle, spacing every line by its own bundle's
+/// line height, such as a large title line followed by smaller body lines.
+pub fn text_on_image_with_custom_line_heights<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    lines: &[(T, &FontBundle<'_>)],
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+) -> TextMeasurement {
+    let widths: Vec<u32> = lines
+        .iter()
+        .map(|(line, font_bundle)| get_text_width(font_bundle, line))
+        .collect();
+    let heights: Vec<i32> = lines
+        .iter()
+        .map(|(_, font_bundle)| get_text_height(font_bundle))
+        .collect();
+    let total_height: i32 = heights.iter().sum();
+    let block_width = justify_width(&widths, WrapBehavior::NoWrap) as i32;
+    let top = pixels_from_top
+        - match vertical_anchor {
+            VerticalAnchor::Top => 0,
+            VerticalAnchor::Center => total_height / 2,
+            VerticalAnchor::Bottom => total_height,
+        };
+    let mut line_y = top;
+    let mut left = pixels_from_left;
+    let mut right = pixels_from_left;
+    for (current_line, ((line, font_bundle), (&width, &height))) in
+        lines.iter().zip(widths.iter().zip(&heights)).enumerate()
+    {
+        let width = width as i32;
+        let is_last_line = current_line + 1 == lines.len();
+        let horizontal_offset = match horizontal_justify {
+            TextJustify::Left | TextJustify::JustifyLastLeft => 0,
+            TextJustify::Center => width / 2,
+            TextJustify::Right => width,
+            TextJustify::JustifyLastRight if is_last_line => width - block_width,
+            TextJustify::JustifyLastCenter if is_last_line => (width - block_width) / 2,
+            TextJustify::JustifyLastRight | TextJustify::JustifyLastCenter => 0,
+        };
+        let stretch_to = if horizontal_justify.is_full_justify() && !is_last_line {
+            Some(block_width as u32)
+        } else {
+            None
+        };
+        let line_x = pixels_from_left - horizontal_offset;
+        draw_line(
+            image,
+            line.as_ref(),
+            font_bundle,
+            line_x,
+            line_y,
+            stretch_to,
+        );
+        left = left.min(line_x);
+        right = right.max(line_x + stretch_to.map_or(width, |stretch| stretch as i32));
+        line_y += height;
+    }
+    TextMeasurement {
+        left,
+        top,
+        width: (right - left) as u32,
+        height: total_height as u32,
+        line_count: lines.len(),
+    }
+}
+
+/// Draws text top to bottom, one character below the next, as in vertical Chinese, Japanese and
+/// Korean writing.
+///
+/// Every character sits in a square cell one line height tall, centered on `pixels_from_left`.
+/// Each new line of `text` starts a new column to the left of the previous one, and
+/// `vertical_anchor` places the tallest column relative to `pixels_from_top`.
+pub fn text_on_image_vertical<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    vertical_anchor: VerticalAnchor,
+    vertical_config: VerticalTextConfig,
+) -> TextMeasurement {
+    let columns: Vec<Vec<char>> = text
+        .as_ref()
+        .lines()
+        .map(|column| column.chars().collect())
+        .collect();
+    let cell_size = get_text_height(font_bundle);
+    let step = cell_size + vertical_config.char_spacing_px;
+    let column_height = |column: &Vec<char>| match column.len() {
+        0 => 0,
+        len => cell_size + step * (len as i32 - 1),
+    };
+    let total_height = columns.iter().map(column_height).max().unwrap_or(0);
+    let top = pixels_from_top
+        - match vertical_anchor {
+            VerticalAnchor::Top => 0,
+            VerticalAnchor::Center => total_height / 2,
+            VerticalAnchor::Bottom => total_height,
+        };
+    let ascent = font_bundle.font.v_metrics(font_bundle.scale).ascent;
+    let mut text_mask = image::GrayImage::new(image.width(), image.height());
+    for (column_index, column) in columns.iter().enumerate() {
+        let cell_left = pixels_from_left - cell_size / 2 - column_index as i32 * cell_size;
+        for (row, &c) in column.iter().enumerate() {
+            let cell_top = top + row as i32 * step;
+            let glyph = font_bundle.font.glyph(c).scaled(font_bundle.scale);
+            let glyph_x = (cell_size as f32 - glyph.h_metrics().advance_width) / 2.;
+            let glyph = glyph.positioned(point(glyph_x, ascent));
+            let mut cell = image::GrayImage::new(cell_size as u32, cell_size as u32);
+            draw_glyph_coverage(&mut cell, &glyph);
+            if vertical_config.rotation_deg != 0. {
+                cell = imageproc::geometric_transformations::rotate_about_center(
+                    &cell,
+                    vertical_config.rotation_deg.to_radians(),
+                    imageproc::geometric_transformations::Interpolation::Bilinear,
+                    image::Luma([0]),
+                );
+            }
+            for (cell_x, cell_y, coverage) in cell.enumerate_pixels() {
+                let (mask_x, mask_y) = (cell_left + cell_x as i32, cell_top + cell_y as i32);
+                if coverage[0] > 0
+                    && (0..image.width() as i32).contains(&mask_x)
+                    && (0..image.height() as i32).contains(&mask_y)
+                {
+                    let pixel = text_mask.get_pixel_mut(mask_x as u32, mask_y as u32);
+                    pixel[0] = pixel[0].max(coverage[0]);
+                }
+            }
+        }
+    }
+    effects::composite_mask(image, &text_mask, font_bundle.color, BlendMode::Normal);
+    TextMeasurement {
+        left: pixels_from_left - cell_size / 2 - (columns.len() as i32 - 1).max(0) * cell_size,
+        top,
+        width: (columns.len() as i32 * cell_size) as u32,
+        height: total_height as u32,
+        line_count: columns.len(),
+    }
+}
+
+/// Helper function to rasterize a glyph's coverage into a mask, clipped to its bounds.
+fn draw_glyph_coverage(mask: &mut image::GrayImage, glyph: &PositionedGlyph<'_>) {
+    if let Some(bounding_box) = glyph.pixel_bounding_box() {
+        let (mask_width, mask_height) = (mask.width() as i32, mask.height() as i32);
+        glyph.draw(|glyph_x, glyph_y, coverage| {
+            let mask_x = glyph_x as i32 + bounding_box.min.x;
+            let mask_y = glyph_y as i32 + bounding_box.min.y;
+            if (0..mask_width).contains(&mask_x) && (0..mask_height).contains(&mask_y) {
+                let pixel = mask.get_pixel_mut(mask_x as u32, mask_y as u32);
+                pixel[0] = pixel[0].max((coverage * 255.).round() as u8);
+            }
+        });
+    }
+}
+
+/// Draws right-to-left text, such as Hebrew or Arabic, treating `pixels_from_left` as the edge
+/// the text starts from. Only available with the `rtl` feature.
+///
+/// Justification is mirrored, so `TextJustify::Left` puts the start of each line, its right
+/// edge, at `pixels_from_left` as if it were `TextJustify::Right`. Each line is reordered for
+/// display with the Unicode bidirectional algorithm. This only handles text that is entirely
+/// right-to-left, and glyphs are not shaped, so Arabic letters are drawn in their isolated forms.
+#[cfg(feature = "rtl")]
+pub fn text_on_image_rtl<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement {
+    let mirrored_justify = match horizontal_justify {
+        TextJustify::Left => TextJustify::Right,
+        TextJustify::Right => TextJustify::Left,
+        TextJustify::JustifyLastLeft => TextJustify::JustifyLastRight,
+        TextJustify::JustifyLastRight => TextJustify::JustifyLastLeft,
+        TextJustify::Center => TextJustify::Center,
+        TextJustify::JustifyLastCenter => TextJustify::JustifyLastCenter,
+    };
+    let lines: Vec<String> = wrap_text_to_lines(text, font_bundle, &wrap_behavior)
+        .iter()
+        .map(|line| reorder_rtl_line(line))
+        .collect();
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let measurement = measure_lines(
+        &line_widths(font_bundle, &lines),
+        get_text_height(font_bundle),
+        pixels_from_left,
+        pixels_from_top,
+        mirrored_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    position_and_draw(
+        image,
+        lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        mirrored_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    measurement
+}
+
+/// Helper function to put a right-to-left line's characters in the order they are drawn.
+#[cfg(feature = "rtl")]
+fn reorder_rtl_line(line: &str) -> String {
+    let bidi_info = unicode_bidi::ParagraphBidiInfo::new(line, Some(unicode_bidi::Level::rtl()));
+    bidi_info.reorder_line(0..line.len()).into_owned()
+}
+
+/// Draws text over a patch of another image, such as a wood grain texture, instead of a solid
+/// background.
+///
+/// The area the text covers is filled with `bg_texture`, starting from `bg_offset_x` and
+/// `bg_offset_y` in the texture and tiling it if the text is larger than the texture.
+pub fn text_on_image_with_background_image<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    bg_texture: &DynamicImage,
+    bg_offset_x: i32,
+    bg_offset_y: i32,
+) -> TextMeasurement {
+    let (texture_width, texture_height) = (bg_texture.width() as i32, bg_texture.height() as i32);
+    if texture_width == 0 || texture_height == 0 {
+        panic!("text_on_image: bg_texture cannot be empty!");
+    }
+    let text = text.as_ref();
+    let measurement = measure_wrapped_text(
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let right = (measurement.left + measurement.width as i32).min(image.width() as i32);
+    let bottom = (measurement.top + measurement.height as i32).min(image.height() as i32);
+    for y in measurement.top.max(0)..bottom {
+        for x in measurement.left.max(0)..right {
+            let texture_x = (bg_offset_x + x - measurement.left).rem_euclid(texture_width);
+            let texture_y = (bg_offset_y + y - measurement.top).rem_euclid(texture_height);
+            let texel = bg_texture.get_pixel(texture_x as u32, texture_y as u32);
+            image.put_pixel(x as u32, y as u32, texel);
+        }
+    }
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text on a fixed grid, putting each character in its own cell regardless of how wide its
+/// glyph is, like a terminal. Useful for code samples or command output.
+///
+/// The character in column `col` of row `row` has its cell's top left corner at
+/// `(pixels_from_left + col * cell_width, pixels_from_top + row * cell_height)`. Each line of
+/// `text` is a row, and tabs advance to the next multiple of 8 columns.
+pub fn text_on_image_monospace<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    config: MonospaceConfig,
+) -> TextMeasurement {
+    const TAB_WIDTH: usize = 8;
+    if config.cell_width == 0 || config.cell_height == 0 {
+        panic!("text_on_image: MonospaceConfig cell_width and cell_height cannot be 0!");
+    }
+    let ascent = font_bundle.font.v_metrics(font_bundle.scale).ascent;
+    let mut columns_used = 0;
+    let mut row_count = 0;
+    for (row, line) in text.as_ref().lines().enumerate() {
+        let mut col = 0;
+        for c in line.chars() {
+            if c == '\t' {
+                col = (col / TAB_WIDTH + 1) * TAB_WIDTH;
+                continue;
+            }
+            let cell_x = pixels_from_left + (col as u32 * config.cell_width) as i32;
+            let cell_y = pixels_from_top + (row as u32 * config.cell_height) as i32;
+            let glyph = font_bundle
+                .font
+                .glyph(c)
+                .scaled(font_bundle.scale)
+                .positioned(point(cell_x as f32, cell_y as f32 + ascent));
+            draw_glyph(image, font_bundle.color, &glyph);
+            col += 1;
+        }
+        columns_used = columns_used.max(col);
+        row_count = row + 1;
+    }
+    TextMeasurement {
+        left: pixels_from_left,
+        top: pixels_from_top,
+        width: columns_used as u32 * config.cell_width,
+        height: row_count as u32 * config.cell_height,
+        line_count: row_count,
+    }
+}
+
+/// Draws text on a single line, replacing part of it with `ellipsis` if it is wider than
+/// `max_width`. Returns the text that was drawn along with its measurement.
+///
+/// As many characters are kept as fit alongside the ellipsis. If not even the ellipsis fits, it
+/// is drawn on its own anyway.
+pub fn text_on_image_ellipsize<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    max_width: u32,
+    ellipsis: &str,
+    position: EllipsisPosition,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+) -> (String, TextMeasurement) {
+    let text = text.as_ref();
+    let truncated = if get_text_width(font_bundle, text) <= max_width {
+        text.to_string()
+    } else {
+        let chars: Vec<char> = text.chars().collect();
+        let keep = |kept: usize| -> String {
+            let (start, end) = match position {
+                EllipsisPosition::Start => (0, kept),
+                EllipsisPosition::Middle => (kept - kept / 2, kept / 2),
+                EllipsisPosition::End => (kept, 0),
+            };
+            let start: String = chars[..start].iter().collect();
+            let end: String = chars[chars.len() - end..].iter().collect();
+            start + ellipsis + &end
+        };
+        // Binary search for the most characters that still fit.
+        let (mut fits, mut too_wide) = (0, chars.len());
+        while too_wide - fits > 1 {
+            let kept = (fits + too_wide) / 2;
+            if get_text_width(font_bundle, keep(kept)) <= max_width {
+                fits = kept;
+            } else {
+                too_wide = kept;
+            }
+        }
+        keep(fits)
+    };
+    let measurement = text_on_image(
+        image,
+        &truncated,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        WrapBehavior::NoWrap,
+    );
+    (truncated, measurement)
+}
+
+/// Draws text wrapped to fit inside a circle, for round badges, avatars and pie chart labels.
+///
+/// Each line wraps to the width of the circle at that line, `2 * sqrt(r² - dy²)` where `dy` is
+/// the distance from the center to the line's edge furthest from it. `vertical_anchor` places
+/// the block against the top or bottom of the circle or centers it, and each line is justified
+/// within its own width. The fewest lines that hold all the text are used. Words too wide for
+/// their line still get one to themselves, and words that do not fit in the circle at all are
+/// left out, which shows up as a smaller `line_count` than expected.
+pub fn text_on_image_in_circle<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    region: CircleRegion,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+) -> TextMeasurement {
+    let line_height = get_text_height(font_bundle);
+    let max_lines = ((2 * region.radius as i32) / line_height).max(1) as usize;
+    let mut layout = Vec::new();
+    for line_count in 1..=max_lines {
+        let (lines, all_fit) = circle_lines(
+            text.as_ref(),
+            font_bundle,
+            region,
+            vertical_anchor,
+            line_count,
+        );
+        layout = lines;
+        if all_fit {
+            break;
+        }
+    }
+    let mut measurement: Option<TextMeasurement> = None;
+    for (current_line, (line, line_y, chord)) in layout.iter().enumerate() {
+        let is_last_line = current_line + 1 == layout.len();
+        let width = get_text_width(font_bundle, line) as i32;
+        let chord_left = region.center_x - *chord as i32 / 2;
+        let line_x = match horizontal_justify {
+            TextJustify::Left | TextJustify::JustifyLastLeft => chord_left,
+            TextJustify::Center => region.center_x - width / 2,
+            TextJustify::Right => chord_left + *chord as i32 - width,
+            TextJustify::JustifyLastRight if is_last_line => chord_left + *chord as i32 - width,
+            TextJustify::JustifyLastCenter if is_last_line => region.center_x - width / 2,
+            TextJustify::JustifyLastRight | TextJustify::JustifyLastCenter => chord_left,
+        };
+        let stretch_to = if horizontal_justify.is_full_justify() && !is_last_line {
+            Some(*chord)
+        } else {
+            None
+        };
+        draw_line(image, line, font_bundle, line_x, *line_y, stretch_to);
+        let right = line_x + stretch_to.map_or(width, |stretch| stretch as i32);
+        measurement = Some(match measurement {
+            None => TextMeasurement {
+                left: line_x,
+                top: *line_y,
+                width: (right - line_x) as u32,
+                height: line_height as u32,
+                line_count: 1,
+            },
+            Some(m) => {
+                let left = m.left.min(line_x);
+                TextMeasurement {
+                    left,
+                    top: m.top,
+                    width: ((m.left + m.width as i32).max(right) - left) as u32,
+                    height: (*line_y + line_height - m.top) as u32,
+                    line_count: m.line_count + 1,
+                }
+            }
+        });
+    }
+    measurement.unwrap_or(TextMeasurement {
+        left: region.center_x,
+        top: region.center_y,
+        ..TextMeasurement::default()
+    })
+}
+
+/// Helper function to fill `line_count` lines inside a circle with words, returning each line
+/// with its top and available width, and whether every word fit.
+fn circle_lines(
+    text: &str,
+    font_bundle: &FontBundle<'_>,
+    region: CircleRegion,
+    vertical_anchor: VerticalAnchor,
+    line_count: usize,
+) -> (Vec<(String, i32, u32)>, bool) {
+    let line_height = get_text_height(font_bundle);
+    let radius = region.radius as i32;
+    let block_height = line_count as i32 * line_height;
+    let block_top = match vertical_anchor {
+        VerticalAnchor::Top => region.center_y - radius,
+        VerticalAnchor::Center => region.center_y - block_height / 2,
+        VerticalAnchor::Bottom => region.center_y + radius - block_height,
+    };
+    let chord_at = |line_top: i32| {
+        let dy = (line_top - region.center_y)
+            .abs()
+            .max((line_top + line_height - region.center_y).abs()) as f32;
+        let radius = radius as f32;
+        if dy >= radius {
+            0
+        } else {
+            (2. * (radius * radius - dy * dy).sqrt()) as u32
+        }
+    };
+    let mut lines = vec![String::new()];
+    for (paragraph_index, paragraph) in text.lines().enumerate() {
+        if paragraph_index > 0 {
+            lines.push(String::new());
+        }
+        for word in paragraph.split_whitespace() {
+            let line_top = block_top + (lines.len() as i32 - 1) * line_height;
+            let current = lines.last_mut().expect("lines starts with one line");
+            let candidate = format!("{} {}", current, word);
+            if current.is_empty() {
+                *current = word.to_string();
+            } else if get_text_width(font_bundle, &candidate) <= chord_at(line_top) {
+                *current = candidate;
+            } else {
+                lines.push(word.to_string());
+            }
+        }
+    }
+    let all_fit = lines.len() <= line_count;
+    lines.truncate(line_count);
+    let lines = lines
+        .into_iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let line_top = block_top + index as i32 * line_height;
+            (line, line_top, chord_at(line_top))
+        })
+        .collect();
+    (lines, all_fit)
+}
+
+/// Draws text wrapped wherever `should_break` asks for a line break, instead of the built-in
+/// whitespace wrapping.
+///
+/// Before adding each word to the current line, `should_break(line, next_word)` is called, and
+/// returning true starts a new line with that word. Newlines in the text always break. The
+/// built-in behavior without hyphenation is
+/// `|line, word| get_text_width(&font_bundle, format!("{line} {word}")) > max_width`. `max_width`
+/// is the block width used for full justification.
+pub fn text_on_image_with_word_wrap_callback<T: AsRef<str>, F: Fn(&str, &str) -> bool>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    max_width: u32,
+    should_break: F,
+) -> TextMeasurement {
+    let mut lines: Vec<String> = Vec::new();
+    for paragraph in text.as_ref().lines() {
+        let mut buffer = String::new();
+        for word in paragraph.split_whitespace() {
+            if buffer.is_empty() {
+                buffer += word;
+            } else if should_break(&buffer, word) {
+                lines.push(std::mem::replace(&mut buffer, word.to_string()));
+            } else {
+                buffer = buffer + " " + word;
+            }
+        }
+        lines.push(buffer);
+    }
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let wrap_behavior = WrapBehavior::Wrap(max_width);
+    let measurement = measure_lines(
+        &line_widths(font_bundle, &lines),
+        get_text_height(font_bundle),
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    position_and_draw(
+        image,
+        lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    measurement
+}
+
+/// Draws left justified text with numbered lines, like a code listing.
+///
+/// The text starts `line_numbers.gutter_width` pixels right of `pixels_from_left`, and each line
+/// of `text` gets its number left aligned in the gutter. Lines that wrap only get a number on
+/// their first row. Returns the area covered by the gutter and text together.
+pub fn text_on_image_with_line_numbers_margin<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    wrap_width: Option<u32>,
+    line_numbers: LineNumberConfig<'_>,
+) -> TextMeasurement {
+    let wrap_behavior = wrap_width.map_or(WrapBehavior::NoWrap, WrapBehavior::Wrap);
+    let line_height = get_text_height(font_bundle);
+    let text_left = pixels_from_left + line_numbers.gutter_width as i32;
+    let mut rows: Vec<String> = Vec::new();
+    for (index, line) in text.as_ref().lines().enumerate() {
+        let number_top = pixels_from_top + rows.len() as i32 * line_height;
+        draw_line(
+            image,
+            &(line_numbers.start + index).to_string(),
+            &line_numbers.font_bundle,
+            pixels_from_left,
+            number_top,
+            None,
+        );
+        rows.extend(wrap_text_to_lines(line, font_bundle, &wrap_behavior));
+    }
+    let rows: Vec<&str> = rows.iter().map(|row| row.as_str()).collect();
+    let text_measurement = measure_lines(
+        &line_widths(font_bundle, &rows),
+        line_height,
+        text_left,
+        pixels_from_top,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        wrap_behavior,
+    );
+    position_and_draw(
+        image,
+        rows,
+        font_bundle,
+        text_left,
+        pixels_from_top,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        wrap_behavior,
+    );
+    if let Some(separator_color) = line_numbers.separator_color {
+        if line_numbers.gutter_width > 0 && text_measurement.height > 0 {
+            blend_filled_rect(
+                image,
+                text_left - 1,
+                pixels_from_top,
+                1,
+                text_measurement.height,
+                separator_color,
+            );
+        }
+    }
+    TextMeasurement {
+        left: pixels_from_left,
+        width: line_numbers.gutter_width + text_measurement.width,
+        ..text_measurement
+    }
+}
+
+/// Draws text wrapped to at most `max_chars` characters per line, preferring to break between
+/// words. Shorthand for [`text_on_image`] with `WrapBehavior::WrapAtCharsPreferWords`.
+pub fn text_on_image_with_max_chars_per_line<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    max_chars: usize,
+) -> TextMeasurement {
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        WrapBehavior::WrapAtCharsPreferWords(max_chars),
+    )
+}
+
+/// Draws text with a text input cursor before the character at `cursor.char_index`.
+///
+/// The cursor is as tall as a line and its left edge sits where the character starts.
+/// `cursor.char_index` counts the characters of `text` like a caret in a text buffer, including
+/// newlines and whitespace left out by wrapping. The cursor goes before the first drawn
+/// character at or after the index, so an index at whitespace dropped at a wrap puts it at the
+/// start of the next line, and an index past the last drawn character puts it at the end of the
+/// text. For empty text it goes where the first line would start.
+pub fn text_on_image_with_cursor<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    cursor: CursorConfig,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let line_height = get_text_height(font_bundle);
+    let line_edges = drawn_char_edges(
+        &lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let cursor_position = wrapped_char_sources(text, &lines)
+        .iter()
+        .zip(&line_edges)
+        .find_map(|(sources, (line_metrics, edges))| {
+            let offset = sources
+                .iter()
+                .position(|source| source.is_some_and(|source| source >= cursor.char_index))?;
+            Some((edges[offset], line_metrics.top))
+        })
+        .or_else(|| {
+            let (line_metrics, edges) = line_edges.last()?;
+            Some((*edges.last()?, line_metrics.top))
+        })
+        .unwrap_or_else(|| {
+            line_positions(
+                &[0],
+                line_height,
+                pixels_from_left,
+                pixels_from_top,
+                horizontal_justify,
+                vertical_anchor,
+                wrap_behavior,
+            )[0]
+        });
+    let measurement = measure_lines(
+        &line_widths(font_bundle, &lines),
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    position_and_draw(
+        image,
+        lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    if cursor.blink_visible {
+        let (cursor_x, cursor_y) = cursor_position;
+        blend_filled_rect(
+            image,
+            cursor_x,
+            cursor_y,
+            cursor.width,
+            line_height as u32,
+            cursor.color,
+        );
+    }
+    measurement
+}
+
+/// Draws text with a box of `highlight_color` behind the character at `char_index`, such as to
+/// point one out in a tutorial.
+///
+/// The box spans the character's advance and the full line height, where the character is
+/// drawn on the wrapped and justified lines. `char_index` counts the characters of `text`,
+/// including newlines and whitespace left out by wrapping. Returns `None` without drawing
+/// anything if `char_index` is past the end of `text` or its character is not drawn, such as
+/// whitespace.
+pub fn text_on_image_with_char_highlight<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    char_index: usize,
+    highlight_color: Rgba<u8>,
+) -> Option<TextMeasurement> {
+    let text = text.as_ref();
+    if text.chars().nth(char_index)?.is_whitespace() {
+        return None;
+    }
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let line_height = get_text_height(font_bundle);
+    let line_edges = drawn_char_edges(
+        &lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let highlight = wrapped_char_sources(text, &lines)
+        .iter()
+        .zip(&line_edges)
+        .find_map(|(sources, (line_metrics, edges))| {
+            let offset = sources
+                .iter()
+                .position(|&source| source == Some(char_index))?;
+            let width = (edges[offset + 1] - edges[offset]) as u32;
+            Some((edges[offset], line_metrics.top, width))
+        });
+    let (left, top, width) = highlight?;
+    blend_filled_rect(image, left, top, width, line_height as u32, highlight_color);
+    let measurement = measure_lines(
+        &line_widths(font_bundle, &lines),
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    position_and_draw(
+        image,
+        lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    Some(measurement)
+}
+
+/// Draws text with each line moved right by the number of pixels `indent` returns for it, such
+/// as indenting only the first line of every paragraph.
+///
+/// `indent` is called with the index of the line among all drawn lines and whether the line
+/// starts a paragraph. When wrapping to a pixel width, each line is wrapped to that width minus
+/// its indent, and a word too wide for its line is split with a hyphen like `WrapBehavior::Wrap`.
+/// Other wrap behaviors wrap as usual and only move the lines.
+pub fn text_on_image_with_line_indent_fn<T: AsRef<str>, F: Fn(usize, bool) -> u32>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    indent: F,
+) -> TextMeasurement {
+    let lines = indented_lines(text.as_ref(), font_bundle, wrap_behavior, indent);
+    let widths: Vec<u32> = lines
+        .iter()
+        .map(|(line, indent)| get_text_width(font_bundle, line) + indent)
+        .collect();
+    let line_height = get_text_height(font_bundle);
+    let positions = line_positions(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let block_width = justify_width(&widths, wrap_behavior);
+    for (index, ((line, indent), &(line_x, line_y))) in lines.iter().zip(&positions).enumerate() {
+        let is_last_line = index + 1 == lines.len();
+        let stretch_to = if horizontal_justify.is_full_justify() && !is_last_line {
+            Some(block_width.saturating_sub(*indent))
+        } else {
+            None
+        };
+        draw_line(
+            image,
+            line,
+            font_bundle,
+            line_x + *indent as i32,
+            line_y,
+            stretch_to,
+        );
+    }
+    measure_lines(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws paragraphs separated by blank lines, wrapped to `wrap_width` pixels, with the first line
+/// of each indented and gaps between them.
+///
+/// Text is split into paragraphs at every `\n\n`, and a single `\n` breaks a line within a
+/// paragraph without indenting the next one. `vertical_anchor` places the whole block, gaps
+/// included. Fully justified text leaves the last line of every paragraph unstretched.
+pub fn text_on_image_with_paragraphs<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_width: u32,
+    config: ParagraphConfig,
+) -> TextMeasurement {
+    let wrap_behavior = WrapBehavior::Wrap(wrap_width);
+    let mut lines: Vec<(String, u32)> = Vec::new();
+    // Whether each line ends its paragraph.
+    let mut ends_paragraph = Vec::new();
+    for paragraph in text.as_ref().split("\n\n") {
+        let paragraph_lines = indented_lines(paragraph, font_bundle, wrap_behavior, |index, _| {
+            if index == 0 {
+                config.first_line_indent_px
+            } else {
+                0
+            }
+        });
+        let line_count = paragraph_lines.len();
+        lines.extend(paragraph_lines);
+        ends_paragraph.extend((0..line_count).map(|index| index + 1 == line_count));
+    }
+    let widths: Vec<u32> = lines
+        .iter()
+        .map(|(line, indent)| get_text_width(font_bundle, line) + indent)
+        .collect();
+    let line_height = get_text_height(font_bundle);
+    let mut offsets = Vec::with_capacity(lines.len());
+    let mut total_gap = 0;
+    for (index, &is_paragraph_end) in ends_paragraph.iter().enumerate() {
+        offsets.push(total_gap);
+        if is_paragraph_end && index + 1 < lines.len() {
+            total_gap += config.paragraph_spacing_px as i32;
+        }
+    }
+    let anchor_shift = match vertical_anchor {
+        VerticalAnchor::Top => 0,
+        VerticalAnchor::Center => total_gap / 2,
+        VerticalAnchor::Bottom => total_gap,
+    };
+    let positions = line_positions(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let block_width = justify_width(&widths, wrap_behavior);
+    for (((line, indent), &(line_x, line_y)), (&offset, &is_paragraph_end)) in lines
+        .iter()
+        .zip(&positions)
+        .zip(offsets.iter().zip(&ends_paragraph))
+    {
+        let stretch_to = if horizontal_justify.is_full_justify() && !is_paragraph_end {
+            Some(block_width.saturating_sub(*indent))
+        } else {
+            None
+        };
+        draw_line(
+            image,
+            line,
+            font_bundle,
+            line_x + *indent as i32,
+            line_y + offset - anchor_shift,
+            stretch_to,
+        );
+    }
+    let measurement = measure_lines(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    TextMeasurement {
+        top: measurement.top - anchor_shift,
+        height: measurement.height + total_gap as u32,
+        ..measurement
+    }
+}
+
+/// Helper function to wrap text for [`text_on_image_with_line_indent_fn`], pairing each line
+/// with its indent.
+fn indented_lines<F: Fn(usize, bool) -> u32>(
+    text: &str,
+    font_bundle: &FontBundle<'_>,
+    wrap_behavior: WrapBehavior,
+    indent: F,
+) -> Vec<(String, u32)> {
+    let mut lines: Vec<(String, u32)> = Vec::new();
+    let Some(max_width) = wrap_behavior.max_width() else {
+        for paragraph in text.lines() {
+            for (index, line) in wrap_text_to_lines(paragraph, font_bundle, &wrap_behavior)
+                .into_iter()
+                .enumerate()
+            {
+                lines.push((line, indent(lines.len(), index == 0)));
+            }
+        }
+        return lines;
+    };
+    let fits = |line: &str, width: u32| get_text_width(font_bundle, line) <= width;
+    for paragraph in text.lines() {
+        let mut words: Vec<String> = paragraph
+            .split_whitespace()
+            .rev()
+            .map(|word| word.to_string())
+            .collect();
+        let mut is_paragraph_first = true;
+        loop {
+            let line_indent = indent(lines.len(), is_paragraph_first);
+            let width = max_width.saturating_sub(line_indent);
+            let mut buffer = String::new();
+            while let Some(word) = words.pop() {
+                let candidate = if buffer.is_empty() {
+                    word.clone()
+                } else {
+                    format!("{} {}", buffer, word)
+                };
+                if fits(&candidate, width) {
+                    buffer = candidate;
+                } else if buffer.is_empty() {
+                    // Split the word, keeping at least one character so every line progresses.
+                    let split = word
+                        .char_indices()
+                        .skip(1)
+                        .map(|(index, _)| index)
+                        .take_while(|&index| fits(&format!("{}-", &word[..index]), width))
+                        .last()
+                        .unwrap_or_else(|| word.chars().next().map_or(0, char::len_utf8));
+                    if split < word.len() {
+                        buffer = format!("{}-", &word[..split]);
+                        words.push(word[split..].to_string());
+                    } else {
+                        buffer = word;
+                    }
+                    break;
+                } else {
+                    words.push(word);
+                    break;
+                }
+            }
+            lines.push((buffer, line_indent));
+            is_paragraph_first = false;
+            if words.is_empty() {
+                break;
+            }
+        }
+    }
+    lines
+}
+
+/// Draws text that mixes scripts by taking each character from the first font in `fonts` that
+/// has a glyph for it, such as a Latin font followed by a Japanese one.
+///
+/// Characters no font has are drawn with the first font. Lines are as tall as the tallest font's
+/// line height and share one baseline, and each run of characters from the same font is drawn
+/// in that font bundle's color. Fully justified lines are not stretched.
+///
+/// Panics if `fonts` is empty.
+pub fn text_on_image_with_font_fallback<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    fonts: &[&FontBundle<'_>],
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement {
+    if fonts.is_empty() {
+        panic!("text_on_image: The font fallback chain needs at least one font!");
+    }
+    let line_width = |line: &str| -> u32 {
+        fallback_runs(fonts, line)
+            .into_iter()
+            .map(|(font_index, run)| get_text_width(fonts[font_index], run))
+            .sum()
+    };
+    let lines = wrap_lines_with(text, &wrap_behavior, line_width);
+    let widths: Vec<u32> = lines.iter().map(|line| line_width(line)).collect();
+    let line_height = fonts
+        .iter()
+        .map(|font_bundle| get_text_height(font_bundle))
+        .max()
+        .unwrap_or(0);
+    let ascent = fonts
+        .iter()
+        .map(|font_bundle| font_bundle.font.v_metrics(font_bundle.scale).ascent)
+        .fold(0., f32::max);
+    let positions = line_positions(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    for (line, &(line_x, line_y)) in lines.iter().zip(&positions) {
+        let runs: Vec<(&FontBundle<'_>, &str)> = fallback_runs(fonts, line)
+            .into_iter()
+            .map(|(font_index, run)| (fonts[font_index], run))
+            .collect();
+        draw_runs(image, &runs, line_x, line_y as f32 + ascent);
+    }
+    measure_lines(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Helper function to split a line into runs that each use one font of a fallback chain, given
+/// as the index of the font and the run's text.
+fn fallback_runs<'t>(fonts: &[&FontBundle<'_>], line: &'t str) -> Vec<(usize, &'t str)> {
+    let font_for = |c: char| {
+        fonts
+            .iter()
+            .position(|font_bundle| {
+                check_font_has_glyphs(font_bundle.font, c.encode_utf8(&mut [0; 4])).is_empty()
+            })
+            .unwrap_or(0)
+    };
+    let mut runs: Vec<(usize, &str)> = Vec::new();
+    let mut run_start = 0;
+    let mut run_font = None;
+    for (index, c) in line.char_indices() {
+        // Whitespace stays in the current run, since every font can draw it.
+        let font_index = match run_font {
+            Some(current) if c.is_whitespace() => current,
+            _ => font_for(c),
+        };
+        match run_font {
+            Some(current) if current != font_index => {
+                runs.push((current, &line[run_start..index]));
+                run_start = index;
+            }
+            _ => {}
+        }
+        run_font = Some(font_index);
+    }
+    if let Some(current) = run_font {
+        runs.push((current, &line[run_start..]));
+    }
+    runs
+}
+
+/// Draws text with each word in the font bundle `select_font` picks for it, such as keywords in
+/// a bold or colored font, and `default_font` for every other word and all whitespace.
+///
+/// Words are split at whitespace, so punctuation is part of the word it touches. Lines are as
+/// tall as the tallest font used anywhere in the text and share one baseline. Fully justified
+/// lines are not stretched.
+pub fn text_on_image_with_per_word_font<'a, T, F>(
+    image: &mut DynamicImage,
+    text: T,
+    default_font: &'a FontBundle<'a>,
+    select_font: F,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement
+where
+    T: AsRef<str>,
+    F: Fn(&str) -> Option<&'a FontBundle<'a>>,
+{
+    let text = text.as_ref();
+    let line_width = |line: &str| -> u32 {
+        word_runs(line, default_font, &select_font)
+            .into_iter()
+            .map(|(font_bundle, run)| get_text_width(font_bundle, run))
+            .sum()
+    };
+    let lines = wrap_lines_with(text, &wrap_behavior, line_width);
+    let widths: Vec<u32> = lines.iter().map(|line| line_width(line)).collect();
+    let used_fonts: Vec<&FontBundle<'_>> = std::iter::once(default_font)
+        .chain(text.split_whitespace().filter_map(&select_font))
+        .collect();
+    let line_height = used_fonts
+        .iter()
+        .map(|font_bundle| get_text_height(font_bundle))
+        .max()
+        .unwrap_or(0);
+    let ascent = used_fonts
+        .iter()
+        .map(|font_bundle| font_bundle.font.v_metrics(font_bundle.scale).ascent)
+        .fold(0., f32::max);
+    let positions = line_positions(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    for (line, &(line_x, line_y)) in lines.iter().zip(&positions) {
+        let runs = word_runs(line, default_font, &select_font);
+        draw_runs(image, &runs, line_x, line_y as f32 + ascent);
+    }
+    measure_lines(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Helper function to split a line into words and the whitespace between them, each with the
+/// font bundle it is drawn in.
+fn word_runs<'a, 't, F: Fn(&str) -> Option<&'a FontBundle<'a>>>(
+    line: &'t str,
+    default_font: &'a FontBundle<'a>,
+    select_font: &F,
+) -> Vec<(&'a FontBundle<'a>, &'t str)> {
+    let mut runs = Vec::new();
+    let mut run_start = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let run_end = chars.peek().map_or(line.len(), |&(index, _)| index);
+        let ends_run = chars
+            .peek()
+            .is_none_or(|&(_, next)| next.is_whitespace() != c.is_whitespace());
+        if ends_run {
+            let run = &line[run_start..run_end];
+            let font_bundle = if c.is_whitespace() {
+                default_font
+            } else {
+                select_font(run).unwrap_or(default_font)
+            };
+            runs.push((font_bundle, run));
+            run_start = run_end;
+        }
+    }
+    runs
+}
+
+/// Draws text narrower than normal, for a condensed look without a condensed font. A
+/// `condense_factor` of 0.8 draws glyphs at 80% of their normal width.
+///
+/// This scales the font horizontally, the same as drawing with a `Scale` whose `x` is
+/// `condense_factor` times its `y`. That is simpler than squeezing a rendered image and keeps the
+/// glyph edges crisp. Wrapping measures the condensed text, so more fits on each line.
+///
+/// Panics if `condense_factor` is not positive.
+pub fn text_on_image_with_condensed<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    condense_factor: f32,
+) -> TextMeasurement {
+    text_on_image(
+        image,
+        text,
+        &horizontally_scaled(font_bundle, condense_factor),
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text wider than normal, the counterpart to [`text_on_image_with_condensed`]. An
+/// `extend_factor` of 1.2 draws glyphs at 120% of their normal width.
+///
+/// Wrapping measures the widened text, so a wrap width holds fewer characters than it would at
+/// the font bundle's own scale. To measure extended text yourself, pass [`get_text_width`] a
+/// font bundle whose scale has the same factor applied.
+///
+/// Panics if `extend_factor` is not positive.
+pub fn text_on_image_with_extended<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    extend_factor: f32,
+) -> TextMeasurement {
+    text_on_image(
+        image,
+        text,
+        &horizontally_scaled(font_bundle, extend_factor),
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Helper function to stretch a font bundle's scale horizontally by `factor`.
+fn horizontally_scaled<'a>(font_bundle: &FontBundle<'a>, factor: f32) -> FontBundle<'a> {
+    if factor <= 0. || !factor.is_finite() {
+        panic!("text_on_image: Horizontal scale factors must be positive!");
+    }
+    let mut font_bundle = *font_bundle;
+    font_bundle.scale.x *= factor;
+    font_bundle
+}
+
+/// Draws text with ASCII punctuation replaced by its typographic forms: straight quotes become
+/// curly quotes, `--` becomes an em dash and `...` becomes an ellipsis.
+///
+/// A quote opens at the start of the text or after whitespace or an opening bracket, and closes
+/// everywhere else, so apostrophes inside words become closing single quotes. Punctuation the
+/// font has no glyph for is left as ASCII.
+pub fn text_on_image_with_smart_quotes<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement {
+    text_on_image(
+        image,
+        smarten_punctuation(text.as_ref(), font_bundle.font),
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Helper function to replace ASCII quotes, dashes and ellipses with the typographic characters
+/// the font can draw.
+fn smarten_punctuation(text: &str, font: &Font<'_>) -> String {
+    let has_glyph = |c: char| font.glyph(c).id() != GlyphId(0);
+    let mut smart = String::with_capacity(text.len());
+    let mut previous: Option<char> = None;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let opens = previous.is_none_or(|p| p.is_whitespace() || "([{".contains(p));
+        let (replacement, consumed) = match c {
+            '"' if opens => ('\u{201C}', 1),
+            '"' => ('\u{201D}', 1),
+            '\'' if opens => ('\u{2018}', 1),
+            '\'' => ('\u{2019}', 1),
+            '-' if rest.starts_with("--") => ('\u{2014}', 2),
+            '.' if rest.starts_with("...") => ('\u{2026}', 3),
+            _ => (c, c.len_utf8()),
+        };
+        if replacement == c || has_glyph(replacement) {
+            smart.push(replacement);
+        } else {
+            smart.push_str(&rest[..consumed]);
+        }
+        previous = rest[..consumed].chars().last();
+        rest = &rest[consumed..];
+    }
+    smart
+}
+
+/// Draws text in small caps, with lowercase letters drawn as uppercase letters at 75% of the
+/// font bundle's scale, sharing the baseline of the full size characters around them.
+pub fn text_on_image_with_small_caps<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement {
+    let mut small_bundle = *font_bundle;
+    small_bundle.scale = Scale {
+        x: font_bundle.scale.x * 0.75,
+        y: font_bundle.scale.y * 0.75,
+    };
+    let bundle_for = |is_small: bool| if is_small { &small_bundle } else { font_bundle };
+    let line_width = |line: &str| -> u32 {
+        small_caps_runs(line)
+            .iter()
+            .map(|(is_small, run)| get_text_width(bundle_for(*is_small), run))
+            .sum()
+    };
+    let lines = wrap_lines_with(text, &wrap_behavior, line_width);
+    let widths: Vec<u32> = lines.iter().map(|line| line_width(line)).collect();
+    let line_height = get_text_height(font_bundle);
+    let ascent = font_bundle.font.v_metrics(font_bundle.scale).ascent;
+    let positions = line_positions(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    for (line, &(line_x, line_y)) in lines.iter().zip(&positions) {
+        let runs = small_caps_runs(line);
+        let runs: Vec<(&FontBundle<'_>, &str)> = runs
+            .iter()
+            .map(|(is_small, run)| (bundle_for(*is_small), run.as_str()))
+            .collect();
+        draw_runs(image, &runs, line_x, line_y as f32 + ascent);
+    }
+    measure_lines(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Helper function to split a line into runs of originally lowercase characters, uppercased and
+/// marked `true`, and runs of everything else, marked `false`.
+fn small_caps_runs(line: &str) -> Vec<(bool, String)> {
+    let mut runs: Vec<(bool, String)> = Vec::new();
+    for c in line.chars() {
+        let is_small = c.is_lowercase();
+        match runs.last_mut() {
+            Some((run_is_small, run)) if *run_is_small == is_small => run.extend(c.to_uppercase()),
+            _ => runs.push((is_small, c.to_uppercase().collect())),
+        }
+    }
+    runs
+}
+
+/// Formats a number with `decimal_places` digits after the decimal separator and the thousands
+/// separator between every group of three digits before it, such as `1,234,567.89`.
+///
+/// Infinite and NaN values are written the way Rust displays them, without separators.
+pub fn format_number(
+    value: f64,
+    decimal_places: usize,
+    thousands_separator: char,
+    decimal_separator: char,
+) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let digits = format!("{:.*}", decimal_places, value.abs());
+    let (whole, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+    let mut formatted = String::new();
+    // Rounding can turn a small negative number into zero, which should not get a sign.
+    if value < 0. && digits.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        formatted.push('-');
+    }
+    for (index, digit) in whole.chars().enumerate() {
+        if index > 0 && (whole.len() - index) % 3 == 0 {
+            formatted.push(thousands_separator);
+        }
+        formatted.push(digit);
+    }
+    if !fraction.is_empty() {
+        formatted.push(decimal_separator);
+        formatted += fraction;
+    }
+    formatted
+}
+
+/// Draws a number formatted by [`format_number`] on a single line.
+pub fn text_on_image_number_formatted(
+    image: &mut DynamicImage,
+    value: f64,
+    decimal_places: usize,
+    thousands_separator: char,
+    decimal_separator: char,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+) -> TextMeasurement {
+    text_on_image(
+        image,
+        format_number(
+            value,
+            decimal_places,
+            thousands_separator,
+            decimal_separator,
+        ),
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        WrapBehavior::NoWrap,
+    )
+}
+
+/// Draws an amount of money with two decimal places, such as `$1,234.50`, with the currency
+/// symbol drawn in its own, usually smaller, font bundle on the same baseline.
+///
+/// The symbol goes before the amount, or after it if `symbol_after` is true. Negative amounts
+/// put the minus sign on the amount, after a leading symbol.
+pub fn text_on_image_currency(
+    image: &mut DynamicImage,
+    value: f64,
+    symbol: &str,
+    font_bundle: &FontBundle<'_>,
+    symbol_font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    symbol_after: bool,
+) -> TextMeasurement {
+    let amount = format_number(value, 2, ',', '.');
+    let runs = if symbol_after {
+        [(font_bundle, amount.as_str()), (symbol_font_bundle, symbol)]
+    } else {
+        [(symbol_font_bundle, symbol), (font_bundle, amount.as_str())]
+    };
+    let widths = [runs
+        .iter()
+        .map(|&(font_bundle, run)| get_text_width(font_bundle, run))
+        .sum()];
+    let line_height = get_text_height(font_bundle).max(get_text_height(symbol_font_bundle));
+    let ascent = font_bundle.font.v_metrics(font_bundle.scale).ascent.max(
+        symbol_font_bundle
+            .font
+            .v_metrics(symbol_font_bundle.scale)
+            .ascent,
+    );
+    let positions = line_positions(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        WrapBehavior::NoWrap,
+    );
+    let (line_x, line_y) = positions[0];
+    draw_runs(image, &runs, line_x, line_y as f32 + ascent);
+    measure_lines(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        WrapBehavior::NoWrap,
+    )
+}
+
+/// Draws a template with every `{key}` placeholder replaced by its value from `values`, such as
+/// `"Hello, {name}!"`.
+///
+/// Keys are everything between a `{` and the next `}`, and values are inserted as they are
+/// without looking for placeholders in them. Placeholders with no value are handled by
+/// `missing_keys`. Returns the keys that were replaced, in the order they appear, along with the
+/// area of the drawn text.
+pub fn text_on_image_with_placeholder<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    template: T,
+    values: &HashMap<String, String>,
+    missing_keys: MissingKeyPolicy,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> Result<(Vec<String>, TextMeasurement), TextOnImageError> {
+    let mut rest = template.as_ref();
+    let mut text = String::with_capacity(rest.len());
+    let mut substituted = Vec::new();
+    while let Some(open) = rest.find('{') {
+        let Some(length) = rest[open + 1..].find('}') else {
+            break;
+        };
+        let key = &rest[open + 1..open + 1 + length];
+        text += &rest[..open];
+        match (values.get(key), missing_keys) {
+            (Some(value), _) => {
+                text += value;
+                substituted.push(key.to_string());
+            }
+            (None, MissingKeyPolicy::Leave) => text += &rest[open..open + length + 2],
+            (None, MissingKeyPolicy::Error) => {
+                return Err(TextOnImageError::MissingPlaceholder {
+                    key: key.to_string(),
+                })
+            }
+            (None, MissingKeyPolicy::ReplaceWithEmpty) => {}
+        }
+        rest = &rest[open + length + 2..];
+    }
+    text += rest;
+    let measurement = text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    Ok((substituted, measurement))
+}
+
+/// Draws text with line `i` in `colors[i % colors.len()]`, such as alternating colors for the
+/// rows of a schedule. With no colors, every line uses the font bundle's color.
+pub fn text_on_image_with_color_per_line<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    colors: &[Rgba<u8>],
+) -> TextMeasurement {
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let metrics = line_metrics(
+        &lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    for line_metrics in metrics {
+        let is_last_line = line_metrics.index + 1 == lines.len();
+        let stretch_to = if horizontal_justify.is_full_justify() && !is_last_line {
+            Some(line_metrics.width)
+        } else {
+            None
+        };
+        let line_bundle = match colors {
+            [] => *font_bundle,
+            _ => font_bundle.with_color(colors[line_metrics.index % colors.len()]),
+        };
+        draw_line(
+            image,
+            &line_metrics.text,
+            &line_bundle,
+            line_metrics.left,
+            line_metrics.top,
+            stretch_to,
+        );
+    }
+    measure_lines(
+        &line_widths(font_bundle, &lines),
+        get_text_height(font_bundle),
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text with extra vertical gaps after chosen lines, such as after a section header.
+///
+/// Each `(line_index, extra_pixels)` pair adds `extra_pixels` below that drawn line, pushing
+/// every later line down. Pairs for the same line add up, and gaps after the last line are
+/// ignored. `vertical_anchor` places the whole block, gaps included.
+pub fn text_on_image_with_spaced_lines<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    extra_spacing: &[(usize, u32)],
+) -> TextMeasurement {
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let widths = line_widths(font_bundle, &lines);
+    let line_height = get_text_height(font_bundle);
+    let gap_after = |line: usize| -> i32 {
+        extra_spacing
+            .iter()
+            .filter(|&&(line_index, _)| line_index == line)
+            .map(|&(_, extra_pixels)| extra_pixels as i32)
+            .sum()
+    };
+    let mut offsets = Vec::with_capacity(lines.len());
+    let mut total_gap = 0;
+    for line in 0..lines.len() {
+        offsets.push(total_gap);
+        if line + 1 < lines.len() {
+            total_gap += gap_after(line);
+        }
+    }
+    let anchor_shift = match vertical_anchor {
+        VerticalAnchor::Top => 0,
+        VerticalAnchor::Center => total_gap / 2,
+        VerticalAnchor::Bottom => total_gap,
+    };
+    let positions: Vec<(i32, i32)> = line_positions(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+    .into_iter()
+    .zip(&offsets)
+    .map(|((line_x, line_y), offset)| (line_x, line_y + offset - anchor_shift))
+    .collect();
+    draw_lines(
+        image,
+        &lines,
+        font_bundle,
+        &positions,
+        horizontal_justify,
+        justify_width(&widths, wrap_behavior),
+    );
+    let measurement = measure_lines(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    TextMeasurement {
+        top: measurement.top - anchor_shift,
+        height: measurement.height + total_gap as u32,
+        ..measurement
+    }
+}
+
+/// Draws text with some ranges of characters raised or lowered, such as footnote markers.
+///
+/// Ranges count the characters of `text`, including newlines and whitespace left out by
+/// wrapping, and hyphens added by wrapping are never shifted. Overlapping ranges are not
+/// supported: a character in several ranges currently uses the last one, but that may change.
+/// Shifts do not change the layout or the returned measurement.
+pub fn text_on_image_with_baseline_shifts<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    shifts: &[BaselineShift],
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let lines: Vec<&str> = lines.iter().map(|line| line.as_str()).collect();
+    let widths = line_widths(font_bundle, &lines);
+    let line_height = get_text_height(font_bundle);
+    let sources = wrapped_char_sources(text, &lines);
+    for placed in placed_glyphs(
+        &lines,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    ) {
+        let shift_px = sources[placed.line_index][placed.char_index].map_or(0, |char_index| {
+            shifts
+                .iter()
+                .rev()
+                .find(|shift| (shift.start_char..shift.end_char).contains(&char_index))
+                .map_or(0, |shift| shift.shift_px)
+        });
+        let position = placed.glyph.position() + rusttype::vector(0., shift_px as f32);
+        let glyph = placed.glyph.into_unpositioned().positioned(position);
+        draw_glyph(image, font_bundle.color, &glyph);
+    }
+    measure_lines(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Decides whether a font bundle suits some text, for [`text_on_image_conditional_font`].
+pub type TextPredicate<'a> = &'a dyn Fn(&str) -> bool;
+
+/// Draws text with the first font bundle in `font_bundles` whose predicate accepts the text, such
+/// as a monospace font for strings that look like code and a display font for short strings.
+///
+/// Predicates are called on the whole text, in order, until one returns `true`. If none does, the
+/// last font bundle is used.
+///
+/// Panics if `font_bundles` is empty.
+pub fn text_on_image_conditional_font<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundles: &[(&FontBundle<'_>, TextPredicate<'_>)],
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let Some(&(fallback, _)) = font_bundles.last() else {
+        panic!("text_on_image: Conditional fonts need at least one font bundle!");
+    };
+    let font_bundle = font_bundles
+        .iter()
+        .find(|(_, predicate)| predicate(text))
+        .map_or(fallback, |&(font_bundle, _)| font_bundle);
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text in capital letters, like CSS `text-transform: uppercase`.
+///
+/// Some characters become more than one when capitalized, such as `ß` becoming `SS`.
+pub fn text_on_image_uppercase<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement {
+    text_on_image(
+        image,
+        text.as_ref().to_uppercase(),
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text with the first letter of each word capitalized, like CSS
+/// `text-transform: capitalize`. Only available with the `capitalize` feature.
+///
+/// Words are found with the Unicode word boundary rules, so `"rock'n'roll"` is one word and
+/// `"semi-final"` is two. The rest of each word is left as written.
+#[cfg(feature = "capitalize")]
+pub fn text_on_image_capitalize<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement {
+    text_on_image(
+        image,
+        capitalize_words(text.as_ref()),
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Helper function to capitalize the first letter of every word.
+#[cfg(feature = "capitalize")]
+fn capitalize_words(text: &str) -> String {
+    let mut capitalized = String::with_capacity(text.len());
+    for word in unicode_segmentation::UnicodeSegmentation::split_word_bounds(text) {
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) if first.is_alphabetic() => {
+                capitalized.extend(first.to_uppercase());
+                capitalized.push_str(chars.as_str());
+            }
+            _ => capitalized.push_str(word),
+        }
+    }
+    capitalized
+}
+
+/// Draws text with a border around it, `padding` pixels out from the text on every side, and no
+/// fill inside.
+///
+/// The border is `border_width` pixels thick, growing outward from the padding. The dashes and
+/// dots of each side start from its top or left end.
+pub fn text_on_image_with_border<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    padding: u32,
+    border_color: Rgba<u8>,
+    border_width: u32,
+    style: BorderStyle,
+) -> TextMeasurement {
+    let measurement = text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let reach = (padding + border_width) as i32;
+    let (left, top) = (measurement.left - reach, measurement.top - reach);
+    let width = measurement.width + 2 * (padding + border_width);
+    let height = measurement.height + 2 * (padding + border_width);
+    let (right, bottom) = (left + width as i32, top + height as i32);
+    let thickness = border_width as i32;
+    // The top and bottom run the full width, so the sides only fill the height between them.
+    let side_length = height.saturating_sub(2 * border_width);
+    for (start, length) in border_segments(style, width, border_width) {
+        let x = left + start as i32;
+        blend_filled_rect(image, x, top, length, border_width, border_color);
+        blend_filled_rect(
+            image,
+            x,
+            bottom - thickness,
+            length,
+            border_width,
+            border_color,
+        );
+    }
+    for (start, length) in border_segments(style, side_length, border_width) {
+        let y = top + thickness + start as i32;
+        blend_filled_rect(image, left, y, border_width, length, border_color);
+        blend_filled_rect(
+            image,
+            right - thickness,
+            y,
+            border_width,
+            length,
+            border_color,
+        );
+    }
+    measurement
+}
+
+/// Helper function to get where each dash of a border side starts and how long it is.
+fn border_segments(style: BorderStyle, side_length: u32, border_width: u32) -> Vec<(u32, u32)> {
+    let (dash_len, gap_len) = match style {
+        BorderStyle::Solid => return vec![(0, side_length)],
+        BorderStyle::Dashed { dash_len, gap_len } => (dash_len, gap_len),
+        BorderStyle::Dotted => (border_width, border_width),
+    };
+    if dash_len == 0 {
+        return Vec::new();
+    }
+    (0..side_length)
+        .step_by((dash_len + gap_len) as usize)
+        .map(|start| (start, dash_len.min(side_length - start)))
+        .collect()
+}
+
+/// Draws text with every character listed in `subs` replaced by its substitute before the text
+/// is wrapped and laid out.
+///
+/// Each character is replaced at most once, so substitutes are not themselves substituted.
+pub fn text_on_image_with_substitution<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    subs: &GlyphSubstitutionMap,
+) -> TextMeasurement {
+    let substituted: String = text
+        .as_ref()
+        .chars()
+        .map(|c| subs.substitutions.get(&c).copied().unwrap_or(c))
+        .collect();
+    text_on_image(
+        image,
+        substituted,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text over a gradient filled rectangle, to keep it readable over a busy image.
+///
+/// The gradient's colors, alpha included, are mixed linearly across the rectangle and blended
+/// over the image.
+pub fn text_on_image_with_gradient_background<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    bg: GradientBackground,
+) -> TextMeasurement {
+    use image::Pixel;
+    let text = text.as_ref();
+    let measurement = measure_wrapped_text(
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let left = measurement.left - bg.padding as i32;
+    let top = measurement.top - bg.padding as i32;
+    let width = (measurement.width + 2 * bg.padding) as i32;
+    let height = (measurement.height + 2 * bg.padding) as i32;
+    let fraction = |position: i32, length: i32| {
+        if length > 1 {
+            position as f32 / (length - 1) as f32
+        } else {
+            0.
+        }
+    };
+    let x_range = left.max(0)..(left + width).min(image.width() as i32);
+    let y_range = top.max(0)..(top + height).min(image.height() as i32);
+    for y in y_range {
+        for x in x_range.clone() {
+            let t = match bg.direction {
+                GradientDirection::Horizontal => fraction(x - left, width),
+                GradientDirection::Vertical => fraction(y - top, height),
+                GradientDirection::Diagonal => fraction(x - left + y - top, width + height - 1),
+            };
+            let color = bg.start_color.map2(&bg.end_color, |start, end| {
+                (start as f32 + (end as f32 - start as f32) * t).round() as u8
+            });
+            let mut pixel = image.get_pixel(x as u32, y as u32);
+            pixel.blend(&color);
+            image.put_pixel(x as u32, y as u32, pixel);
+        }
+    }
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text over rows of alternating colors, one row per line.
+///
+/// Every row is as wide as the widest line, so the rows line up into a block. Rows meet at the
+/// line boundaries without overlapping, so translucent colors blend evenly.
+pub fn text_on_image_with_zebra<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    zebra: ZebraConfig,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let measurement = measure_wrapped_text(
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let line_height = get_text_height(font_bundle);
+    let padding_y = zebra.padding_y as i32;
+    for line in 0..measurement.line_count {
+        let mut top = measurement.top + line as i32 * line_height;
+        let mut bottom = top + line_height;
+        if line == 0 {
+            top -= padding_y;
+        }
+        if line + 1 == measurement.line_count {
+            bottom += padding_y;
+        }
+        let color = if line % 2 == 0 {
+            zebra.even_color
+        } else {
+            zebra.odd_color
+        };
+        blend_filled_rect(
+            image,
+            measurement.left - zebra.padding_x as i32,
+            top,
+            measurement.width + 2 * zebra.padding_x,
+            (bottom - top) as u32,
+            color,
+        );
+    }
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text over a rectangle filled with a repeating pattern, `padding` pixels larger than the
+/// text on every side.
+///
+/// The pattern starts from the rectangle's top left corner and is blended over the image. Sizes
+/// and widths below 1 are treated as 1.
+pub fn text_on_image_with_tile_background<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    padding: u32,
+    pattern: TilePattern,
+) -> TextMeasurement {
+    use image::Pixel;
+    let text = text.as_ref();
+    let measurement = measure_wrapped_text(
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let left = measurement.left - padding as i32;
+    let top = measurement.top - padding as i32;
+    let width = (measurement.width + 2 * padding) as i32;
+    let height = (measurement.height + 2 * padding) as i32;
+    let pattern_color = |x: i32, y: i32| match pattern {
+        TilePattern::Checkerboard {
+            size,
+            color_a,
+            color_b,
+        } => {
+            let size = size.max(1) as i32;
+            if (x / size + y / size) % 2 == 0 {
+                color_a
+            } else {
+                color_b
+            }
+        }
+        TilePattern::Stripes {
+            width,
+            angle_deg,
+            color_a,
+            color_b,
+        } => {
+            let (sin, cos) = angle_deg.to_radians().sin_cos();
+            // Measured from pixel centers, so no pixel sits on the edge between two stripes.
+            let across = (y as f32 + 0.5) * cos - (x as f32 + 0.5) * sin;
+            if (across / width.max(1) as f32).floor() as i64 % 2 == 0 {
+                color_a
+            } else {
+                color_b
+            }
+        }
+    };
+    let x_range = left.max(0)..(left + width).min(image.width() as i32);
+    let y_range = top.max(0)..(top + height).min(image.height() as i32);
+    for y in y_range {
+        for x in x_range.clone() {
+            let mut pixel = image.get_pixel(x as u32, y as u32);
+            pixel.blend(&pattern_color(x - left, y - top));
+            image.put_pixel(x as u32, y as u32, pixel);
+        }
+    }
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text in black or white, whichever stands out more from the part of the image it covers.
+///
+/// The average luminance of the pixels under the text's line boxes picks the color: black over
+/// backgrounds brighter than 128 and white over the rest. Transparency is ignored, and text that
+/// is entirely off the image is drawn black.
+pub fn text_on_image_auto_contrast<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font: &Font<'_>,
+    scale: Scale,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement {
+    let text = text.as_ref();
+    let mut font_bundle = FontBundle::new(font, scale, Rgba([0, 0, 0, 255]));
+    let area = measure_wrapped_text(
+        text,
+        &font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let x_range = area.left.max(0)..(area.left + area.width as i32).min(image.width() as i32);
+    let y_range = area.top.max(0)..(area.top + area.height as i32).min(image.height() as i32);
+    let mut total_luminance = 0.;
+    let mut pixel_count = 0;
+    for y in y_range {
+        for x in x_range.clone() {
+            let pixel = image.get_pixel(x as u32, y as u32);
+            total_luminance +=
+                0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32;
+            pixel_count += 1;
+        }
+    }
+    if pixel_count > 0 && total_luminance / pixel_count as f32 <= 128. {
+        font_bundle.set_color(Rgba([255, 255, 255, 255]));
+    }
+    text_on_image(
+        image,
+        text,
+        &font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text like [`text_on_image`], but returns an error instead of drawing anything if the
+/// text cannot be laid out the way `wrap_behavior` asks.
+///
+/// Wrap settings that [`text_on_image`] would panic on give `TextOnImageError::InvalidWrap`.
+/// With `WrapBehavior::WrapStrictNoHyphen`, any word wider than the wrap width gives
+/// `TextOnImageError::WordTooWide`.
+pub fn text_on_image_try<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> Result<TextMeasurement, TextOnImageError> {
+    let too_narrow = match wrap_behavior {
+        WrapBehavior::Wrap(max_width) | WrapBehavior::WrapOptimal(max_width) => {
+            max_width < get_text_width(font_bundle, "mm")
+        }
+        WrapBehavior::WrapAtCharsPreferWords(max_chars) => max_chars == 0,
+        WrapBehavior::NoWrap | WrapBehavior::WrapStrictNoHyphen(_) => false,
+    };
+    if too_narrow {
+        return Err(TextOnImageError::InvalidWrap(wrap_behavior));
+    }
+    if let WrapBehavior::WrapStrictNoHyphen(max_width) = wrap_behavior {
+        for word in text.as_ref().split_whitespace() {
+            let width = get_text_width(font_bundle, word);
+            if width > max_width {
+                return Err(TextOnImageError::WordTooWide {
+                    word: word.to_string(),
+                    width,
+                    max_width,
+                });
+            }
+        }
+    }
+    Ok(text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    ))
+}
+
+/// Draws text wrapped to `max_width` without ever splitting a word, or returns
+/// `TextOnImageError::WordTooWide` if a word cannot fit. Shorthand for [`text_on_image_try`] with
+/// `WrapBehavior::WrapStrictNoHyphen`.
+pub fn text_on_image_wrapped_with_hard_max<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    max_width: u32,
+) -> Result<TextMeasurement, TextOnImageError> {
+    text_on_image_try(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        WrapBehavior::WrapStrictNoHyphen(max_width),
+    )
+}
+
+/// Draws text like [`text_on_image`], first logging a warning for each character the font has
+/// no glyph for. Only available with the `log` feature.
+#[cfg(feature = "log")]
+pub fn text_on_image_with_missing_glyph_warning<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement {
+    for missing in check_font_has_glyphs(font_bundle.font, text.as_ref()) {
+        log::warn!(
+            "text_on_image: Font has no glyph for {:?} (U+{:04X}), it will not be drawn",
+            missing,
+            missing as u32
+        );
+    }
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+/// Draws text on an image on top of a pill shaped background, like a caption bubble or badge.
+///
+/// Returns the area of the pill, padding included, with the text's line count.
+pub fn text_on_image_pill<T: AsRef<str>>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    pill: PillConfig,
+) -> TextMeasurement {
+    let measurement = measure_wrapped_text(
+        text.as_ref(),
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let left = measurement.left - pill.h_padding as i32;
+    let top = measurement.top - pill.v_padding as i32;
+    let width = measurement.width + 2 * pill.h_padding;
+    let height = measurement.height + 2 * pill.v_padding;
+    draw_filled_pill(image, left, top, width, height, pill.border_color);
+    if width > 2 * pill.border_width && height > 2 * pill.border_width {
+        draw_filled_pill(
+            image,
+            left + pill.border_width as i32,
+            top + pill.border_width as i32,
+            width - 2 * pill.border_width,
+            height - 2 * pill.border_width,
+            pill.background,
+        );
+    }
+    text_on_image(
+        image,
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    TextMeasurement {
+        left,
+        top,
+        width,
+        height,
+        ..measurement
+    }
+}
+
+/// Helper function to draw a rectangle with fully rounded ends.
+///
+/// imageproc has no rounded rectangle primitive, so the ends are drawn as ellipses.
+fn draw_filled_pill(
+    image: &mut DynamicImage,
+    left: i32,
+    top: i32,
+    width: u32,
+    height: u32,
+    color: Rgba<u8>,
+) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    let radius = (height.min(width) / 2) as i32;
+    let center_y = top + height as i32 / 2;
+    let height_radius = (height as i32 - 1) / 2;
+    let body_width = width as i32 - 2 * radius;
+    if body_width > 0 {
+        draw_filled_rect_mut(
+            image,
+            Rect::at(left + radius, top).of_size(body_width as u32, height),
+            color,
+        );
+    }
+    draw_filled_ellipse_mut(
+        image,
+        (left + radius, center_y),
+        radius,
+        height_radius,
+        color,
+    );
+    draw_filled_ellipse_mut(
+        image,
+        (left + width as i32 - 1 - radius, center_y),
+        radius,
+        height_radius,
+        color,
+    );
+}
+
+/// Helper function to alpha blend a filled rectangle over the image, clipped to its bounds.
+fn blend_filled_rect(
+    image: &mut DynamicImage,
+    left: i32,
+    top: i32,
+    width: u32,
+    height: u32,
+    color: Rgba<u8>,
+) {
+    use image::Pixel;
+    let x_range = left.max(0)..(left + width as i32).min(image.width() as i32);
+    let y_range = top.max(0)..(top + height as i32).min(image.height() as i32);
+    for y in y_range {
+        for x in x_range.clone() {
+            let mut pixel = image.get_pixel(x as u32, y as u32);
+            pixel.blend(&color);
+            image.put_pixel(x as u32, y as u32, pixel);
+        }
+    }
+}
+
+fn position_and_draw(
+    image: &mut DynamicImage,
+    lines: Vec<&str>,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) {
+    let widths = line_widths(font_bundle, &lines);
+    let positions = line_positions(
+        &widths,
+        get_text_height(font_bundle),
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let block_width = justify_width(&widths, wrap_behavior);
+    draw_lines(
+        image,
+        &lines,
+        font_bundle,
+        &positions,
+        horizontal_justify,
+        block_width,
+    );
+}
+
+/// Helper function to draw lines whose top left corners have already been found.
+fn draw_lines(
+    image: &mut DynamicImage,
+    lines: &[&str],
+    font_bundle: &FontBundle<'_>,
+    positions: &[(i32, i32)],
+    horizontal_justify: TextJustify,
+    block_width: u32,
+) {
+    for (current_line, (&line, &(line_x, line_y))) in lines.iter().zip(positions).enumerate() {
+        debug_log!("{} width: {}", line, get_text_width(font_bundle, line));
+        let is_last_line = current_line + 1 == lines.len();
+        let stretch_to = if horizontal_justify.is_full_justify() && !is_last_line {
+            Some(block_width)
+        } else {
+            None
+        };
+        draw_line(image, line, font_bundle, line_x, line_y, stretch_to);
+        debug_log!("pixels_from_left for line {}: {}", line, line_x);
+    }
+}
+
+/// Helper function to draw one line, spreading its words out to fill `stretch_to` if given.
+fn draw_line(
+    image: &mut DynamicImage,
+    line: &str,
+    font_bundle: &FontBundle<'_>,
+    line_x: i32,
+    line_y: i32,
+    stretch_to: Option<u32>,
+) {
+    match stretch_to {
+        Some(block_width) => {
+            draw_justified_line(image, line, font_bundle, line_x, line_y, block_width)
+        }
+        None => draw_text_mut(
+            image,
+            font_bundle.color,
+            line_x,
+            line_y,
+            font_bundle.scale,
+            font_bundle.font,
+            line,
+        ),
+    }
+}
+
+/// Helper function to draw a line with its words spread out to fill `block_width`.
+fn draw_justified_line(
+    image: &mut DynamicImage,
+    line: &str,
+    font_bundle: &FontBundle<'_>,
+    line_x: i32,
+    line_y: i32,
+    block_width: u32,
+) {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let gap = justified_gap(font_bundle, &words, block_width);
+    let mut word_x = line_x as f32;
+    for word in words {
+        draw_text_mut(
+            image,
+            font_bundle.color,
+            word_x.round() as i32,
+            line_y,
+            font_bundle.scale,
+            font_bundle.font,
+            word,
+        );
+        word_x += get_text_width(font_bundle, word) as f32 + gap;
+    }
+}
+
+/// Helper function to get the space between words that stretches them to `block_width`.
+fn justified_gap(font_bundle: &FontBundle<'_>, words: &[&str], block_width: u32) -> f32 {
+    let words_width: u32 = words
+        .iter()
+        .map(|&word| get_text_width(font_bundle, word))
+        .sum();
+    if words.len() > 1 {
+        block_width.saturating_sub(words_width) as f32 / (words.len() - 1) as f32
+    } else {
+        0.
+    }
+}
+
+/// Helper function to get the width fully justified lines are stretched to.
+fn justify_width(line_widths: &[u32], wrap_behavior: WrapBehavior) -> u32 {
+    let widest_line = line_widths.iter().copied().max().unwrap_or(0);
+    wrap_behavior
+        .max_width()
+        .map_or(widest_line, |max_width| max_width.max(widest_line))
+}
+
+/// Helper function to get the top left corner each line is drawn from.
+fn line_positions(
+    line_widths: &[u32],
+    line_height: i32,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> Vec<(i32, i32)> {
+    let lines_len = line_widths.len() as i32;
+    let block_width = justify_width(line_widths, wrap_behavior) as i32;
+    line_widths
+        .iter()
+        .enumerate()
+        .map(|(current_line, &line_width)| {
+            let current_line = current_line as i32;
+            let is_last_line = current_line + 1 == lines_len;
+            let vertical_offset = match vertical_anchor {
+                VerticalAnchor::Top => line_height * current_line,
+                VerticalAnchor::Center => {
+                    (line_height * current_line - line_height * (lines_len - current_line)) / 2
+                }
+                VerticalAnchor::Bottom => -(line_height * (lines_len - current_line)),
+            };
+            let line_width = line_width as i32;
+            let horizontal_offset = match horizontal_justify {
+                TextJustify::Left | TextJustify::JustifyLastLeft => 0,
+                TextJustify::Center => line_width / 2,
+                TextJustify::Right => line_width,
+                TextJustify::JustifyLastRight if is_last_line => line_width - block_width,
+                TextJustify::JustifyLastCenter if is_last_line => (line_width - block_width) / 2,
+                TextJustify::JustifyLastRight | TextJustify::JustifyLastCenter => 0,
+            };
+            (
+                pixels_from_left - horizontal_offset,
+                pixels_from_top + vertical_offset,
+            )
+        })
+        .collect()
+}
+
+/// Helper function to get the width of each line.
+fn line_widths(font_bundle: &FontBundle<'_>, lines: &[&str]) -> Vec<u32> {
+    lines
+        .iter()
+        .map(|&line| get_text_width(font_bundle, line))
+        .collect()
+}
+
+/// Helper function to get the metrics of lines that have already been wrapped.
+fn line_metrics(
+    lines: &[&str],
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> Vec<LineMetrics> {
+    let widths = line_widths(font_bundle, lines);
+    let line_height = get_text_height(font_bundle);
+    let positions = line_positions(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let block_width = justify_width(&widths, wrap_behavior);
+    let ascent = font_bundle.font.v_metrics(font_bundle.scale).ascent as i32;
+    lines
+        .iter()
+        .zip(widths.iter().zip(&positions))
+        .enumerate()
+        .map(|(index, (&line, (&width, &(left, top))))| {
+            let is_last_line = index + 1 == lines.len();
+            LineMetrics {
+                text: line.to_string(),
+                index,
+                left,
+                top,
+                width: if horizontal_justify.is_full_justify() && !is_last_line {
+                    block_width
+                } else {
+                    width
+                },
+                height: line_height as u32,
+                baseline: top + ascent,
+            }
+        })
+        .collect()
+}
+
+/// Helper function to measure lines that have already been wrapped.
+fn measure_lines(
+    line_widths: &[u32],
+    line_height: i32,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> TextMeasurement {
+    let positions = line_positions(
+        line_widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let left = positions
+        .iter()
+        .map(|&(line_x, _)| line_x)
+        .min()
+        .unwrap_or(pixels_from_left);
+    let block_width = justify_width(line_widths, wrap_behavior);
+    let right = line_widths
+        .iter()
+        .zip(&positions)
+        .enumerate()
+        .map(|(current_line, (&line_width, &(line_x, _)))| {
+            let is_last_line = current_line + 1 == line_widths.len();
+            if horizontal_justify.is_full_justify() && !is_last_line {
+                line_x + block_width as i32
+            } else {
+                line_x + line_width as i32
+            }
+        })
+        .max()
+        .unwrap_or(pixels_from_left);
+    let top = positions
+        .first()
+        .map(|&(_, line_y)| line_y)
+        .unwrap_or(pixels_from_top);
+    TextMeasurement {
+        left,
+        top,
+        width: (right - left) as u32,
+        height: (line_height * line_widths.len() as i32) as u32,
+        line_count: line_widths.len(),
+    }
+}
+
+/// Helper function to lay out a line glyph by glyph, starting from `origin` on the baseline.
+///
+/// `spacing(previous, current, kerning)` returns how far to move between two characters on top
+/// of the previous glyph's advance, where `kerning` is the font's own kerning for the pair.
+fn layout_glyphs<'a, F: Fn(char, char, f32) -> f32>(
+    font_bundle: &FontBundle<'a>,
+    line: &str,
+    origin: Point<f32>,
+    spacing: &F,
+) -> Vec<PositionedGlyph<'a>> {
+    let mut glyphs = Vec::new();
+    let mut pen_x = origin.x;
+    let mut previous: Option<(char, GlyphId)> = None;
+    for current in line.chars() {
+        let glyph = font_bundle.font.glyph(current).scaled(font_bundle.scale);
+        if let Some((previous_char, previous_id)) = previous {
+            let kerning = font_bundle
+                .font
+                .pair_kerning(font_bundle.scale, previous_id, glyph.id());
+            pen_x += spacing(previous_char, current, kerning);
+        }
+        previous = Some((current, glyph.id()));
+        let advance_width = glyph.h_metrics().advance_width;
+        glyphs.push(glyph.positioned(point(pen_x, origin.y)));
+        pen_x += advance_width;
+    }
+    glyphs
+}
+
+/// Helper function to get the width of glyphs laid out from an origin of 0.
+fn glyphs_width(glyphs: &[PositionedGlyph<'_>]) -> u32 {
+    glyphs
+        .last()
+        .map(|glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+        .unwrap_or(0.) as u32
+}
+
+/// Helper function to draw one glyph, blending it the same way `draw_text_mut` does.
+fn draw_glyph(image: &mut DynamicImage, color: Rgba<u8>, glyph: &PositionedGlyph<'_>) {
+    if let Some(bounding_box) = glyph.pixel_bounding_box() {
+        let (image_width, image_height) = (image.width() as i32, image.height() as i32);
+        glyph.draw(|glyph_x, glyph_y, coverage| {
+            let image_x = glyph_x as i32 + bounding_box.min.x;
+            let image_y = glyph_y as i32 + bounding_box.min.y;
+            if (0..image_width).contains(&image_x) && (0..image_height).contains(&image_y) {
+                let pixel = image.get_pixel(image_x as u32, image_y as u32);
+                let weighted_color = weighted_sum(pixel, color, 1.0 - coverage, coverage);
+                image.put_pixel(image_x as u32, image_y as u32, weighted_color);
+            }
+        });
+    }
+}
+
+/// Helper function to draw runs of text one after another on a shared baseline, each with its
+/// own font bundle.
+fn draw_runs(
+    image: &mut DynamicImage,
+    runs: &[(&FontBundle<'_>, &str)],
+    line_x: i32,
+    baseline: f32,
+) {
+    let kerning_only = |_, _, kerning| kerning;
+    let mut pen_x = line_x as f32;
+    for &(font_bundle, run) in runs {
+        for glyph in layout_glyphs(font_bundle, run, point(pen_x, baseline), &kerning_only) {
+            draw_glyph(image, font_bundle.color, &glyph);
+        }
+        pen_x += get_text_width(font_bundle, run) as f32;
+    }
+}
+
+/// Helper function to wrap, position and draw text glyph by glyph with custom spacing.
+///
+/// Fully justified lines are stretched to the same width as in [`text_on_image`], with the extra
+/// width shared out between their spaces.
+fn text_on_image_spaced<T: AsRef<str>, F: Fn(char, char, f32) -> f32>(
+    image: &mut DynamicImage,
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+    spacing: F,
+) -> TextMeasurement {
+    let spaced_width =
+        |line: &str| glyphs_width(&layout_glyphs(font_bundle, line, point(0., 0.), &spacing));
+    let lines = wrap_lines_with(text, &wrap_behavior, spaced_width);
+    let widths: Vec<u32> = lines.iter().map(|line| spaced_width(line)).collect();
+    let line_height = get_text_height(font_bundle);
+    let positions = line_positions(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let ascent = font_bundle.font.v_metrics(font_bundle.scale).ascent;
+    let block_width = justify_width(&widths, wrap_behavior);
+    for (index, (line, &(line_x, line_y))) in lines.iter().zip(&positions).enumerate() {
+        let is_last_line = index + 1 == lines.len();
+        let space_count = line.chars().filter(|c| c.is_whitespace()).count();
+        let extra_per_space =
+            if horizontal_justify.is_full_justify() && !is_last_line && space_count > 0 {
+                block_width.saturating_sub(widths[index]) as f32 / space_count as f32
+            } else {
+                0.
+            };
+        let stretched = |previous: char, current, kerning| {
+            let extra = if previous.is_whitespace() {
+                extra_per_space
+            } else {
+                0.
+            };
+            spacing(previous, current, kerning) + extra
+        };
+        let origin = point(line_x as f32, line_y as f32 + ascent);
+        for glyph in layout_glyphs(font_bundle, line, origin, &stretched) {
+            draw_glyph(image, font_bundle.color, &glyph);
+        }
+    }
+    measure_lines(
+        &widths,
+        line_height,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    )
+}
+
+mod effects;
+pub use effects::*;
+#[cfg(feature = "config")]
+mod config;
+#[cfg(feature = "config")]
+pub use config::*;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "json")]
+pub use json::*;
+#[cfg(feature = "hyphenation")]
+mod hyphenate;
+#[cfg(feature = "hyphenation")]
+pub use hyphenate::*;
+#[cfg(feature = "syntax")]
+mod syntax;
+#[cfg(feature = "syntax")]
+pub use syntax::*;
+#[cfg(feature = "rand")]
+mod jitter;
+#[cfg(feature = "rand")]
+pub use jitter::*;
+#[cfg(feature = "svg")]
+mod svg;
+#[cfg(feature = "svg")]
+pub use svg::*;
+
 #[cfg(test)]
 mod test;
@@ -0,0 +1,147 @@
+//! Describing text as SVG markup instead of drawing it. Enabled with the `svg` feature.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::{
+    get_text_height, measure_wrapped_text, wrap_text_to_lines, FontBundle, TextJustify,
+    VerticalAnchor, WrapBehavior,
+};
+
+/// Builds an SVG `<text>` element placing text where [`text_on_image`](crate::text_on_image)
+/// would draw it.
+///
+/// Wrapped text gets one `<tspan>` per line, each a line height below the last with `dy`. The
+/// element sets the font size and fill color but no font family, so give it one from the
+/// surrounding document, such as with [`svg_document`]. The SVG renderer lays out the glyphs
+/// itself, so lines wrapped here may come out slightly wider or narrower than in a raster
+/// image, and fully justified text is drawn left aligned without stretching.
+pub fn text_to_svg<T: AsRef<str>>(
+    text: T,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    horizontal_justify: TextJustify,
+    vertical_anchor: VerticalAnchor,
+    wrap_behavior: WrapBehavior,
+) -> String {
+    let text = text.as_ref();
+    let lines = wrap_text_to_lines(text, font_bundle, &wrap_behavior);
+    let measurement = measure_wrapped_text(
+        text,
+        font_bundle,
+        pixels_from_left,
+        pixels_from_top,
+        horizontal_justify,
+        vertical_anchor,
+        wrap_behavior,
+    );
+    let text_anchor = match horizontal_justify {
+        TextJustify::Center => "middle",
+        TextJustify::Right => "end",
+        // Fully justified text is placed by its left edge.
+        _ => "start",
+    };
+    let font = font_bundle.font;
+    let unscaled = font.v_metrics_unscaled();
+    // Scales in this crate measure the ascent to descent height, while SVG sizes the em square.
+    let font_size =
+        font_bundle.scale.y * font.units_per_em() as f32 / (unscaled.ascent - unscaled.descent);
+    let ascent = font.v_metrics(font_bundle.scale).ascent as i32;
+    let baseline = measurement.top + ascent;
+    let color = font_bundle.color();
+    let mut svg = format!(
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"{}\" font-size=\"{}\" fill=\"#{:02x}{:02x}{:02x}\"",
+        pixels_from_left,
+        baseline,
+        text_anchor,
+        (font_size * 100.).round() / 100.,
+        color[0],
+        color[1],
+        color[2],
+    );
+    if color[3] < 255 {
+        svg += &format!(
+            " fill-opacity=\"{}\"",
+            (color[3] as f32 / 255. * 1000.).round() / 1000.
+        );
+    }
+    svg += ">";
+    if let [line] = lines.as_slice() {
+        svg += &escape_xml(line);
+    } else {
+        let line_height = get_text_height(font_bundle);
+        let mut pending_dy = 0;
+        let mut is_first = true;
+        for line in &lines {
+            // Empty tspans hold no characters to carry dy, so their height moves to the next.
+            if line.is_empty() {
+                pending_dy += line_height;
+                continue;
+            }
+            if is_first {
+                svg += &format!("<tspan x=\"{}\"", pixels_from_left);
+                if pending_dy != 0 {
+                    svg += &format!(" dy=\"{}\"", pending_dy);
+                }
+                svg += ">";
+                is_first = false;
+            } else {
+                svg += &format!(
+                    "<tspan x=\"{}\" dy=\"{}\">",
+                    pixels_from_left,
+                    pending_dy + line_height
+                );
+            }
+            svg += &escape_xml(line);
+            svg += "</tspan>";
+            pending_dy = 0;
+        }
+    }
+    svg += "</text>";
+    svg
+}
+
+/// Wraps SVG elements, such as those from [`text_to_svg`], in a complete SVG document.
+///
+/// If `embedded_font` is given as a font family name and the font file's bytes, the font is
+/// inlined as base64 and used for all text, so the document renders the same without the font
+/// installed.
+pub fn svg_document(
+    width: u32,
+    height: u32,
+    embedded_font: Option<(&str, &[u8])>,
+    body: &str,
+) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">",
+        width, height
+    );
+    if let Some((family, data)) = embedded_font {
+        let family = escape_xml(family);
+        svg += &format!(
+            "<style>@font-face{{font-family:\"{0}\";src:url(data:font/ttf;base64,{1});}}text{{font-family:\"{0}\";}}</style>",
+            family,
+            STANDARD.encode(data)
+        );
+    }
+    svg += body;
+    svg += "</svg>";
+    svg
+}
+
+/// Helper function to escape the characters XML gives special meaning.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&apos;",
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
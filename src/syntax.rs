@@ -0,0 +1,81 @@
+//! Drawing syntax highlighted code using syntect. Enabled with the `syntax` feature.
+
+use std::sync::OnceLock;
+
+use image::{DynamicImage, Rgba};
+use rusttype::point;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use crate::{
+    draw_glyph, get_text_height, get_text_width, layout_glyphs, FontBundle, TextMeasurement,
+};
+
+/// Draws code colored by syntect's highlighting, with its top left corner at `pixels_from_left`
+/// and `pixels_from_top`.
+///
+/// `language` is a syntax name or file extension such as `"rs"` or `"Python"`, and falls back to
+/// plain text if syntect does not know it. `theme` names one of syntect's default themes, such
+/// as `"base16-ocean.dark"` or `"InspiredGitHub"`. Tabs expand to `tab_width` spaces. The color
+/// of `font_bundle` is not used.
+///
+/// Panics if `theme` is not one of the default themes.
+pub fn text_on_image_syntax_highlighted(
+    image: &mut DynamicImage,
+    code: &str,
+    language: &str,
+    theme: &str,
+    font_bundle: &FontBundle<'_>,
+    pixels_from_left: i32,
+    pixels_from_top: i32,
+    tab_width: usize,
+) -> TextMeasurement {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+    let syntax_set = SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines);
+    let theme_set = THEME_SET.get_or_init(ThemeSet::load_defaults);
+    let theme = theme_set.themes.get(theme).unwrap_or_else(|| {
+        panic!(
+            "text_on_image: Unknown syntax highlighting theme {}!",
+            theme
+        )
+    });
+    let syntax = syntax_set
+        .find_syntax_by_token(language)
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let code = code.replace('\t', &" ".repeat(tab_width));
+    let kerning_only = |_, _, kerning| kerning;
+    let line_height = get_text_height(font_bundle);
+    let ascent = font_bundle.font.v_metrics(font_bundle.scale).ascent;
+    let mut width = 0;
+    let mut line_count = 0;
+    for (index, line) in LinesWithEndings::from(&code).enumerate() {
+        let baseline = pixels_from_top as f32 + (index as i32 * line_height) as f32 + ascent;
+        let mut pen_x = pixels_from_left as f32;
+        // Highlighting only fails on malformed syntax definitions, which the defaults are not.
+        let ranges = highlighter
+            .highlight_line(line, syntax_set)
+            .expect("text_on_image: The default syntax definitions could not be parsed!");
+        for (style, piece) in ranges {
+            let piece = piece.trim_end_matches(['\n', '\r']);
+            let foreground = style.foreground;
+            let color = Rgba([foreground.r, foreground.g, foreground.b, foreground.a]);
+            for glyph in layout_glyphs(font_bundle, piece, point(pen_x, baseline), &kerning_only) {
+                draw_glyph(image, color, &glyph);
+            }
+            pen_x += get_text_width(font_bundle, piece) as f32;
+        }
+        width = width.max((pen_x - pixels_from_left as f32) as u32);
+        line_count = index + 1;
+    }
+    TextMeasurement {
+        left: pixels_from_left,
+        top: pixels_from_top,
+        width,
+        height: (line_count as i32 * line_height) as u32,
+        line_count,
+    }
+}
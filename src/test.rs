@@ -1,5 +1,5 @@
 use crate::*;
-use image::{ImageError, Rgba};
+use image::{GenericImageView, ImageError, Rgba};
 use rusttype::{Font, Scale};
 
 #[derive(Debug)]
@@ -8,9 +8,27 @@ enum PossibleErrors {
     ImageSaveFailure(ImageError),
 }
 
+impl std::fmt::Display for PossibleErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PossibleErrors::ImageOpeningError(err) => write!(f, "could not open image: {}", err),
+            PossibleErrors::ImageSaveFailure(err) => write!(f, "could not save image: {}", err),
+        }
+    }
+}
+
 const FONT: &[u8] = include_bytes!("../assets/BitstreamVeraSansMonoBold-pq1a.ttf");
 
+fn save_output(image: &DynamicImage, name: &str) -> Result<(), PossibleErrors> {
+    std::fs::create_dir_all("./output")
+        .map_err(|err| PossibleErrors::ImageSaveFailure(ImageError::IoError(err)))?;
+    image
+        .save(format!("./output/{}", name))
+        .map_err(PossibleErrors::ImageSaveFailure)
+}
+
 #[test]
+#[allow(clippy::redundant_closure)]
 fn test_example_text() -> Result<(), PossibleErrors> {
     let mut background = image::open("assets/background.png")
         .map_err(|err| PossibleErrors::ImageOpeningError(err))?;
@@ -44,3 +62,4217 @@ fn test_negative_scale() {
     let font = Font::try_from_vec(font).unwrap();
     let _font_bundle = FontBundle::new(&font, Scale { x: -40., y: 40. }, Rgba([0, 255, 0, 255]));
 }
+
+#[test]
+fn test_pill() -> Result<(), PossibleErrors> {
+    let mut background =
+        image::open("assets/background.png").map_err(PossibleErrors::ImageOpeningError)?;
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([255, 255, 255, 255]));
+    let pill = PillConfig {
+        background: Rgba([40, 40, 200, 255]),
+        border_color: Rgba([255, 255, 255, 255]),
+        border_width: 3,
+        h_padding: 20,
+        v_padding: 8,
+    };
+    let pill_measurement = text_on_image_pill(
+        &mut background,
+        "Badge",
+        &font_bundle,
+        400,
+        200,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        pill,
+    );
+    let measurement = measure_wrapped_text(
+        "Badge",
+        &font_bundle,
+        400,
+        200,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    let border = background.get_pixel(400, (measurement.top - 8) as u32).0;
+    assert_eq!(border, [255, 255, 255, 255]);
+    let fill = background.get_pixel((measurement.left - 10) as u32, 200).0;
+    assert_eq!(fill, [40, 40, 200, 255]);
+    assert_eq!(pill_measurement.left, measurement.left - 20);
+    assert_eq!(pill_measurement.top, measurement.top - 8);
+    assert_eq!(pill_measurement.width, measurement.width + 40);
+    assert_eq!(pill_measurement.height, measurement.height + 16);
+    assert_eq!(pill_measurement.line_count, 1);
+    save_output(&background, "test_pill.png")
+}
+
+#[test]
+fn test_text_renderer_batch() {
+    let mut background = DynamicImage::new_rgba8(400, 400);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let renderer = TextRenderer::new(
+        font_bundle,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    let measurements = renderer.render_batch(&mut background, &[("One", 10, 10), ("Two", 10, 100)]);
+    assert_eq!(measurements.len(), 2);
+    assert_eq!(measurements[0].left, 10);
+    assert_eq!(measurements[1].top, 100);
+    assert_eq!(measurements[0].height, measurements[1].height);
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn test_config_file() -> Result<(), TextOnImageError> {
+    std::fs::create_dir_all("./output")?;
+    std::fs::write(
+        "./output/test_config_file.toml",
+        r#"
+text = "From a config file"
+font_path = "../assets/BitstreamVeraSansMonoBold-pq1a.ttf"
+scale = 30.0
+color = [0, 0, 255, 255]
+x = 100
+y = 100
+justify = "Left"
+anchor = "Top"
+"#,
+    )?;
+    let mut background = DynamicImage::new_rgba8(800, 200);
+    let measurement =
+        text_on_image_from_config_file(&mut background, "./output/test_config_file.toml")?;
+    assert_eq!(measurement.left, 100);
+    assert_eq!(measurement.top, 100);
+    assert_eq!(measurement.line_count, 1);
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json_colors() -> Result<(), TextOnImageError> {
+    let mut background = DynamicImage::new_rgba8(400, 200);
+    let measurement = text_on_image_from_json(
+        &mut background,
+        r##"{
+            "text": "JSON",
+            "font_path": "assets/BitstreamVeraSansMonoBold-pq1a.ttf",
+            "scale": 30.0,
+            "color": "#ff000080",
+            "x": 200,
+            "y": 100
+        }"##,
+    )?;
+    assert_eq!(measurement.line_count, 1);
+    let result = text_on_image_from_json(
+        &mut background,
+        r#"{
+            "text": "JSON",
+            "font_path": "assets/BitstreamVeraSansMonoBold-pq1a.ttf",
+            "scale": 30.0,
+            "color": "red",
+            "x": 200,
+            "y": 100
+        }"#,
+    );
+    assert!(matches!(result, Err(TextOnImageError::JsonError(_))));
+    Ok(())
+}
+
+#[test]
+fn test_align_bottom_right() {
+    let mut background = DynamicImage::new_rgba8(400, 200);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([255, 255, 255, 255]));
+    let measurement =
+        text_on_image_align_bottom_right(&mut background, "(c) 2024", &font_bundle, 15);
+    save_output(&background, "test_align_bottom_right.png").unwrap();
+    assert_eq!(measurement.left + measurement.width as i32, 400 - 15);
+    assert_eq!(measurement.top + measurement.height as i32, 200 - 15);
+    assert_eq!(measurement.line_count, 1);
+}
+
+#[test]
+fn test_progress_label() {
+    let mut background = DynamicImage::new_rgba8(400, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let prefix_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([0, 255, 0, 255]));
+    let suffix_bundle =
+        FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([255, 255, 255, 255]));
+    let (prefix, suffix) = text_on_image_progress_label(
+        &mut background,
+        "75%",
+        " complete",
+        &prefix_bundle,
+        &suffix_bundle,
+        10,
+        50,
+        VerticalAnchor::Center,
+    );
+    assert_eq!(prefix.left, 10);
+    assert_eq!(suffix.left, prefix.left + prefix.width as i32);
+    assert_eq!(prefix.top, suffix.top);
+}
+
+#[test]
+fn test_columns_overflow() {
+    let mut background = DynamicImage::new_rgba8(600, 200);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let config = ColumnConfig {
+        num_columns: 2,
+        column_width: 150,
+        column_gap: 20,
+        origin_x: 10,
+        origin_y: 10,
+        max_height: 50,
+    };
+    let layout = text_on_image_columns(
+        &mut background,
+        "one\ntwo\nthree\nfour\nfive\nsix\nseven",
+        &font_bundle,
+        config,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+    );
+    assert_eq!(layout.columns_used, 2);
+    assert!(layout.text_truncated);
+    assert_eq!(layout.remaining_text.as_deref(), Some("five\nsix\nseven"));
+}
+
+#[test]
+fn test_align_to() {
+    let mut background = DynamicImage::new_rgba8(600, 400);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let first = text_on_image(
+        &mut background,
+        "First",
+        &font_bundle,
+        300,
+        200,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    let right = text_on_image_align_to(
+        &mut background,
+        "Right",
+        &font_bundle,
+        &first,
+        RelativeAlignment::RightOf { gap: 5 },
+        WrapBehavior::NoWrap,
+    );
+    assert_eq!(right.left, first.left + first.width as i32 + 5);
+    assert_eq!(right.top, first.top);
+    let below = text_on_image_align_to(
+        &mut background,
+        "Below",
+        &font_bundle,
+        &first,
+        RelativeAlignment::Below { gap: 5 },
+        WrapBehavior::NoWrap,
+    );
+    assert_eq!(below.top, first.top + first.height as i32 + 5);
+    let above = text_on_image_align_to(
+        &mut background,
+        "Above",
+        &font_bundle,
+        &first,
+        RelativeAlignment::Above { gap: 5 },
+        WrapBehavior::NoWrap,
+    );
+    assert_eq!(above.top + above.height as i32, first.top - 5);
+}
+
+#[test]
+fn test_tracking_widths() {
+    let mut background = DynamicImage::new_rgba8(800, 200);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([0, 0, 0, 255]));
+    let mut tracked_width = |tracking_em| {
+        text_on_image_with_tracking(
+            &mut background,
+            "Tracking",
+            &font_bundle,
+            400,
+            100,
+            TextJustify::Center,
+            VerticalAnchor::Center,
+            WrapBehavior::NoWrap,
+            tracking_em,
+        )
+        .width
+    };
+    let normal = tracked_width(0.0);
+    let loose = tracked_width(0.1);
+    let tight = tracked_width(-0.05);
+    assert!(loose > normal);
+    assert!(tight < normal);
+}
+
+#[test]
+fn test_tracking_stretches_justified_lines() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let text = "one two three four five six seven";
+    let line_height = get_text_height(&font_bundle) as u32;
+    // The rightmost inked column of the first line.
+    let first_line_end = |image: &DynamicImage| {
+        image
+            .pixels()
+            .filter(|&(_, y, pixel)| y >= 10 && y < 10 + line_height && pixel[3] > 128)
+            .map(|(x, _, _)| x)
+            .max()
+            .unwrap()
+    };
+    let mut plain = DynamicImage::new_rgba8(300, 150);
+    text_on_image(
+        &mut plain,
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::JustifyLastLeft,
+        VerticalAnchor::Top,
+        WrapBehavior::Wrap(200),
+    );
+    let mut tracked = DynamicImage::new_rgba8(300, 150);
+    text_on_image_with_tracking(
+        &mut tracked,
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::JustifyLastLeft,
+        VerticalAnchor::Top,
+        WrapBehavior::Wrap(200),
+        0.,
+    );
+    save_output(&tracked, "test_tracking_stretches_justified_lines.png").unwrap();
+    assert!(first_line_end(&tracked).abs_diff(first_line_end(&plain)) <= 1);
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_highlight_matches() {
+    let mut background = DynamicImage::new_rgba8(600, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([0, 0, 0, 255]));
+    let pattern = regex::Regex::new("cat").unwrap();
+    let measurement = text_on_image_highlight_matches(
+        &mut background,
+        "a cat sat",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        &pattern,
+        Rgba([255, 255, 0, 255]),
+    );
+    let positions = get_char_positions(&font_bundle, "a cat sat");
+    assert_eq!(
+        background
+            .get_pixel(10 + positions[2] + 1, measurement.top as u32 + 1)
+            .0,
+        [255, 255, 0, 255]
+    );
+    assert_eq!(
+        background
+            .get_pixel(10 + positions[0] + 1, measurement.top as u32 + 1)
+            .0,
+        [0, 0, 0, 0]
+    );
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_highlight_matches_justified() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let highlight_color = Rgba([255, 255, 0, 255]);
+    let text = "one two three four five six seven";
+    let wrap_behavior = WrapBehavior::Wrap(200);
+    let lines = wrap_text_to_lines(text, &font_bundle, &wrap_behavior);
+    assert!(lines.len() > 1);
+    // The last word on the first line, which stretching moves furthest.
+    let word = lines[0].rsplit(' ').next().unwrap();
+    let word_start = lines[0].len() - word.len();
+    let mut word_span = (u32::MAX, 0);
+    text_on_image_with_glyph_callback(
+        &mut DynamicImage::new_rgba8(300, 100),
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::JustifyLastRight,
+        VerticalAnchor::Top,
+        wrap_behavior,
+        |_, glyph| {
+            if glyph.line_index == 0 && glyph.char_index >= word_start {
+                let advance = glyph.glyph.unpositioned().h_metrics().advance_width as u32;
+                word_span.0 = word_span.0.min(glyph.draw_x as u32);
+                word_span.1 = word_span.1.max(glyph.draw_x as u32 + advance);
+            }
+        },
+    );
+    assert!(word_span.0 > 10 + get_char_positions(&font_bundle, &lines[0])[word_start]);
+    let mut background = DynamicImage::new_rgba8(300, 100);
+    let pattern = regex::Regex::new(&format!(r"\b{}\b", regex::escape(word))).unwrap();
+    text_on_image_highlight_matches(
+        &mut background,
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::JustifyLastRight,
+        VerticalAnchor::Top,
+        wrap_behavior,
+        &pattern,
+        highlight_color,
+    );
+    save_output(&background, "test_highlight_matches_justified.png").unwrap();
+    assert_eq!(background.get_pixel(word_span.0, 11), highlight_color);
+    assert_eq!(background.get_pixel(word_span.1 - 1, 11), highlight_color);
+    assert_ne!(background.get_pixel(word_span.0 - 1, 11), highlight_color);
+    assert_ne!(background.get_pixel(word_span.1, 11), highlight_color);
+}
+
+#[test]
+fn test_color_alpha() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let mut font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([10, 20, 30, 255]));
+    let faded = font_bundle.with_color_alpha(64);
+    assert_eq!(faded.color(), Rgba([10, 20, 30, 64]));
+    assert_eq!(font_bundle.color(), Rgba([10, 20, 30, 255]));
+    font_bundle.set_color_alpha(128);
+    assert_eq!(font_bundle.color(), Rgba([10, 20, 30, 128]));
+}
+
+#[test]
+fn test_configured_decorations() -> Result<(), PossibleErrors> {
+    let mut background =
+        image::open("assets/background.png").map_err(PossibleErrors::ImageOpeningError)?;
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 60., y: 60. }, Rgba([255, 255, 255, 255]));
+    let measurement = text_on_image_configured(
+        &mut background,
+        "Decorated",
+        &font_bundle,
+        400,
+        400,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        Some(ShadowConfig {
+            offset_x: 4,
+            offset_y: 4,
+            color: Rgba([0, 0, 0, 255]),
+            blur_radius: None,
+        }),
+        Some(OutlineConfig {
+            width: 2,
+            color: Rgba([200, 0, 0, 255]),
+        }),
+        Some(BackgroundConfig {
+            color: Rgba([0, 0, 120, 255]),
+            padding: 10,
+        }),
+        Some(UnderlineConfig {
+            color: Rgba([255, 255, 0, 255]),
+            thickness: 3,
+            offset: 6,
+        }),
+    );
+    let corner = background
+        .get_pixel((measurement.left - 5) as u32, (measurement.top - 5) as u32)
+        .0;
+    assert_eq!(corner, [0, 0, 120, 255]);
+    save_output(&background, "test_configured_decorations.png")
+}
+
+#[test]
+fn test_configured_underline_justified() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let yellow = Rgba([255, 255, 0, 255]);
+    let text = "one two three four five six seven";
+    let lines = wrap_text_to_lines(text, &font_bundle, &WrapBehavior::Wrap(200));
+    assert!(lines.len() > 1);
+    let mut background = DynamicImage::new_rgba8(300, 150);
+    text_on_image_configured(
+        &mut background,
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::JustifyLastLeft,
+        VerticalAnchor::Top,
+        WrapBehavior::Wrap(200),
+        None,
+        None,
+        None,
+        Some(UnderlineConfig {
+            color: yellow,
+            thickness: 2,
+            offset: 2,
+        }),
+    );
+    save_output(&background, "test_configured_underline_justified.png").unwrap();
+    let ascent = font.v_metrics(font_bundle.scale).ascent as u32;
+    let line_height = get_text_height(&font_bundle) as u32;
+    let underline_end = |line: u32| {
+        (0..300)
+            .filter(|&x| background.get_pixel(x, 10 + line * line_height + ascent + 2) == yellow)
+            .max()
+            .unwrap()
+    };
+    // The stretched first line is underlined to the wrap width, and the last line to its end.
+    assert_eq!(underline_end(0), 10 + 200 - 1);
+    let last = lines.len() as u32 - 1;
+    assert_eq!(
+        underline_end(last),
+        10 + get_text_width(&font_bundle, lines.last().unwrap()) - 1
+    );
+}
+
+#[cfg(feature = "default-font")]
+#[test]
+fn test_default_font_bundle() {
+    let mut background = DynamicImage::new_rgba8(200, 50);
+    let font_bundle = FontBundle::default();
+    assert_eq!(font_bundle.color(), Rgba([0, 0, 0, 255]));
+    let measurement = text_on_image(
+        &mut background,
+        "hello",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    assert!(measurement.width > 0);
+}
+
+#[test]
+fn test_justify_single_line_fallback() {
+    let mut background = DynamicImage::new_rgba8(600, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let last_left = text_on_image(
+        &mut background,
+        "Only line",
+        &font_bundle,
+        50,
+        50,
+        TextJustify::JustifyLastLeft,
+        VerticalAnchor::Center,
+        WrapBehavior::Wrap(300),
+    );
+    assert_eq!(last_left.left, 50);
+    let last_right = text_on_image(
+        &mut background,
+        "Only line",
+        &font_bundle,
+        50,
+        50,
+        TextJustify::JustifyLastRight,
+        VerticalAnchor::Center,
+        WrapBehavior::Wrap(300),
+    );
+    assert_eq!(last_right.left + last_right.width as i32, 350);
+}
+
+#[test]
+fn test_justify_fills_wrap_width() {
+    let mut background = DynamicImage::new_rgba8(600, 200);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let measurement = text_on_image(
+        &mut background,
+        "Justified text spreads its words across the whole width",
+        &font_bundle,
+        50,
+        20,
+        TextJustify::JustifyLastLeft,
+        VerticalAnchor::Top,
+        WrapBehavior::Wrap(300),
+    );
+    assert!(measurement.line_count > 1);
+    assert_eq!(measurement.left, 50);
+    assert_eq!(measurement.width, 300);
+}
+
+#[test]
+fn test_max_font_size_shrinks() {
+    let mut background = DynamicImage::new_rgba8(400, 400);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let (font_bundle, measurement) = text_on_image_with_max_font_size(
+        &mut background,
+        "This text is far too long to fit at the maximum size",
+        &font,
+        Rgba([0, 0, 0, 255]),
+        200,
+        200,
+        80.,
+        300,
+        100,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+    );
+    assert!(font_bundle.scale().x < 80.);
+    assert!(measurement.width <= 300);
+    assert!(measurement.height <= 100);
+}
+
+#[test]
+fn test_line_spacing_ratio() {
+    let mut background = DynamicImage::new_rgba8(400, 400);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let mut spaced = |ratio| {
+        text_on_image_vertically_centered_lines(
+            &mut background,
+            "One\nTwo\nThree",
+            &font_bundle,
+            200,
+            200,
+            TextJustify::Center,
+            WrapBehavior::NoWrap,
+            ratio,
+        )
+    };
+    let normal = spaced(1.0);
+    let loose = spaced(1.5);
+    assert!(loose.height > normal.height);
+    assert!(loose.top < normal.top);
+}
+
+#[test]
+fn test_enum_u8_round_trip() {
+    for value in 0..=5u8 {
+        let justify = TextJustify::try_from(value).unwrap();
+        assert_eq!(u8::from(justify), value);
+    }
+    for value in 0..=2u8 {
+        let anchor = VerticalAnchor::try_from(value).unwrap();
+        assert_eq!(u8::from(anchor), value);
+    }
+    assert!(matches!(
+        TextJustify::try_from(6),
+        Err(TextOnImageError::UnknownVariant { value: 6, .. })
+    ));
+    assert!(matches!(
+        VerticalAnchor::try_from(3),
+        Err(TextOnImageError::UnknownVariant { value: 3, .. })
+    ));
+}
+
+#[test]
+fn test_line_callback() {
+    let mut background = DynamicImage::new_rgba8(400, 400);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let mut seen = Vec::new();
+    text_on_image_with_line_callback(
+        &mut background,
+        "First\nSecond",
+        &font_bundle,
+        100,
+        100,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        |line, _image| seen.push(line),
+    );
+    assert_eq!(seen.len(), 2);
+    assert_eq!(seen[0].text, "First");
+    assert_eq!(seen[1].top, seen[0].top + seen[0].height as i32);
+    assert!(seen[0].baseline > seen[0].top);
+}
+
+#[test]
+fn test_bounding_box_matches_painted_pixels() {
+    let mut background = DynamicImage::new_rgba8(400, 200);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([0, 0, 0, 255]));
+    let bounding_box = get_text_bounding_box(
+        "Boxed\ntext",
+        &font_bundle,
+        200,
+        100,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    text_on_image(
+        &mut background,
+        "Boxed\ntext",
+        &font_bundle,
+        200,
+        100,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    for (x, y, pixel) in background.pixels() {
+        if pixel.0[3] > 0 {
+            let (x, y) = (x as i32, y as i32);
+            assert!(x >= bounding_box.left && x < bounding_box.right);
+            assert!(y >= bounding_box.top && y < bounding_box.bottom);
+        }
+    }
+    let other = TextBoundingBox {
+        left: bounding_box.right - 1,
+        top: bounding_box.top,
+        right: bounding_box.right + 10,
+        bottom: bounding_box.bottom,
+    };
+    assert!(bounding_box.intersects(&other));
+}
+
+#[test]
+fn test_avoid_overlap() {
+    let mut background = DynamicImage::new_rgba8(400, 400);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let taken = get_text_bounding_box(
+        "Label",
+        &font_bundle,
+        200,
+        200,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    let moved = text_on_image_avoid_overlap(
+        &mut background,
+        "Label",
+        &font_bundle,
+        200,
+        200,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        &[taken],
+        100,
+    );
+    assert!(moved.is_some());
+    let blocked = text_on_image_avoid_overlap(
+        &mut background,
+        "Label",
+        &font_bundle,
+        200,
+        200,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        &[taken],
+        2,
+    );
+    assert!(blocked.is_none());
+}
+
+#[test]
+fn test_optimal_wrap_is_more_even() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let text = "aaa bb cc ddddd";
+    let max_width = get_text_width(&font_bundle, "aaaaaa");
+    let raggedness = |lines: &[String]| -> u64 {
+        lines[..lines.len() - 1]
+            .iter()
+            .map(|line| {
+                let unused = (max_width - get_text_width(&font_bundle, line)) as u64;
+                unused * unused
+            })
+            .sum()
+    };
+    let greedy = wrap_text_to_lines(text, &font_bundle, &WrapBehavior::Wrap(max_width));
+    let optimal = wrap_text_greedy_optimal(text, &font_bundle, max_width);
+    assert_eq!(greedy, vec!["aaa bb", "cc", "ddddd"]);
+    assert_eq!(optimal, vec!["aaa", "bb cc", "ddddd"]);
+    assert!(raggedness(&optimal) < raggedness(&greedy));
+}
+
+#[test]
+fn test_animated_frames() {
+    let base = DynamicImage::new_rgba8(200, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([255, 0, 0, 255]));
+    let frames: Vec<AnimationFrame> = (0..3)
+        .map(|i| AnimationFrame {
+            text: format!("Frame {}", i),
+            x: 100,
+            y: 50,
+            justify: TextJustify::Center,
+            anchor: VerticalAnchor::Center,
+            delay_ms: 100,
+        })
+        .collect();
+    let rendered =
+        text_on_image_animated_frames(&base, &frames, &font_bundle, WrapBehavior::NoWrap);
+    assert_eq!(rendered.len(), 3);
+    assert_ne!(rendered[0].image.as_bytes(), base.as_bytes());
+    let gif_frame: image::Frame = rendered[0].clone().into();
+    assert_eq!(gif_frame.delay().numer_denom_ms(), (100, 1));
+}
+
+#[test]
+fn test_block_height() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let one_line = measure_text_height_for_lines(1, &font_bundle);
+    assert_eq!(measure_text_height_for_lines(3, &font_bundle), 3 * one_line);
+    assert_eq!(
+        measure_wrapped_block_height("a\nb\nc", &font_bundle, &WrapBehavior::NoWrap),
+        3 * one_line
+    );
+}
+
+#[test]
+fn test_kerning_override() {
+    let mut background = DynamicImage::new_rgba8(400, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([0, 0, 0, 255]));
+    let mut kerning = KerningOverrides::default();
+    let plain = text_on_image_with_kerning(
+        &mut background,
+        "AVA",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        &kerning,
+    );
+    kerning.pairs.insert(('A', 'V'), 10.);
+    let kerned = text_on_image_with_kerning(
+        &mut background,
+        "AVA",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        &kerning,
+    );
+    assert_eq!(kerned.width, plain.width + 10);
+}
+
+#[test]
+fn test_custom_line_heights() {
+    let mut background = DynamicImage::new_rgba8(400, 300);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let title = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([0, 0, 0, 255]));
+    let body = FontBundle::new(&font, Scale { x: 16., y: 16. }, Rgba([0, 0, 0, 255]));
+    let measurement = text_on_image_with_custom_line_heights(
+        &mut background,
+        &[("Title", &title), ("Body one", &body), ("Body two", &body)],
+        200,
+        20,
+        TextJustify::Center,
+        VerticalAnchor::Top,
+    );
+    assert_eq!(
+        measurement.height,
+        measure_text_height_for_lines(1, &title) + measure_text_height_for_lines(2, &body)
+    );
+}
+
+#[test]
+fn test_missing_glyphs() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    assert!(check_font_has_glyphs(&font, "Plain text\nwith a newline").is_empty());
+    assert_eq!(check_font_has_glyphs(&font, "日本 日"), vec!['日', '本']);
+}
+
+#[test]
+fn test_shrink_font_to_single_line() {
+    let mut background = DynamicImage::new_rgba8(400, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let text = "A title that must stay on one line";
+    let font_bundle = text_on_image_shrink_font_to_single_line(
+        &mut background,
+        text,
+        &font,
+        Rgba([0, 0, 0, 255]),
+        10,
+        50,
+        380,
+        60.,
+        6.,
+        VerticalAnchor::Center,
+    )
+    .unwrap();
+    assert!(font_bundle.scale().x < 60.);
+    assert!(
+        measure_wrapped_text(
+            text,
+            &font_bundle,
+            10,
+            50,
+            TextJustify::Left,
+            VerticalAnchor::Center,
+            WrapBehavior::NoWrap
+        )
+        .width
+            <= 380
+    );
+    let result = text_on_image_shrink_font_to_single_line(
+        &mut background,
+        text,
+        &font,
+        Rgba([0, 0, 0, 255]),
+        10,
+        50,
+        20,
+        60.,
+        6.,
+        VerticalAnchor::Center,
+    );
+    assert!(matches!(result, Err(TextOnImageError::CannotFitText)));
+}
+
+#[test]
+fn test_blend_modes() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([128, 128, 128, 255]));
+    let backdrop = Rgba([200, 100, 50, 255]);
+    let (mask, _) = render_text_to_mask(
+        200,
+        100,
+        "Blend",
+        &font_bundle,
+        100,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    let (covered_x, covered_y, _) = mask
+        .enumerate_pixels()
+        .find(|(_, _, coverage)| coverage[0] == 255)
+        .unwrap();
+    let blended_pixel = |blend| {
+        let mut background =
+            DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(200, 100, backdrop));
+        text_on_image_with_blend(
+            &mut background,
+            "Blend",
+            &font_bundle,
+            100,
+            50,
+            TextJustify::Center,
+            VerticalAnchor::Center,
+            WrapBehavior::NoWrap,
+            blend,
+        );
+        background.get_pixel(covered_x, covered_y)
+    };
+    assert_eq!(blended_pixel(BlendMode::Normal), Rgba([128, 128, 128, 255]));
+    // Multiplying by mid gray roughly halves each channel, screening brightens it.
+    let multiplied = blended_pixel(BlendMode::Multiply);
+    assert!(multiplied[0] < backdrop[0] && multiplied[2] < backdrop[2]);
+    let screened = blended_pixel(BlendMode::Screen);
+    assert!(screened[0] > backdrop[0] && screened[2] > backdrop[2]);
+}
+
+#[test]
+fn test_inner_shadow() {
+    let mut background = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+        300,
+        100,
+        Rgba([40, 40, 40, 255]),
+    ));
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 60., y: 60. }, Rgba([230, 230, 230, 255]));
+    let inner_shadow = InnerShadowConfig {
+        offset_x: 3,
+        offset_y: 3,
+        color: Rgba([0, 0, 0, 200]),
+        blur: 1.5,
+    };
+    text_on_image_with_inner_shadow(
+        &mut background,
+        "Inset",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        inner_shadow,
+    );
+    save_output(&background, "test_inner_shadow.png").unwrap();
+    let (mask, _) = render_text_to_mask(
+        300,
+        100,
+        "Inset",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    // Some covered pixels are shaded darker than the text color, and nothing outside the glyphs is.
+    let shaded_inside = mask
+        .enumerate_pixels()
+        .any(|(x, y, coverage)| coverage[0] == 255 && background.get_pixel(x, y)[0] < 200);
+    assert!(shaded_inside);
+    let mut plain = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+        300,
+        100,
+        Rgba([40, 40, 40, 255]),
+    ));
+    text_on_image(
+        &mut plain,
+        "Inset",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    let untouched_outside = mask
+        .enumerate_pixels()
+        .filter(|(_, _, coverage)| coverage[0] == 0)
+        .all(|(x, y, _)| background.get_pixel(x, y) == plain.get_pixel(x, y));
+    assert!(untouched_outside);
+}
+
+#[test]
+fn test_emboss() {
+    let gray = Rgba([128, 128, 128, 255]);
+    let mut background = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(300, 100, gray));
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 60., y: 60. }, gray);
+    text_on_image_with_emboss(
+        &mut background,
+        "Relief",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        90.,
+        80.,
+    );
+    save_output(&background, "test_emboss.png").unwrap();
+    let (mask, _) = render_text_to_mask(
+        300,
+        100,
+        "Relief",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    // Lit from above, the top of a glyph catches the light and its bottom is in shade.
+    let column = (0..300)
+        .find(|&x| (0..100).any(|y| mask.get_pixel(x, y)[0] == 255))
+        .unwrap();
+    let covered: Vec<u32> = (0..100)
+        .filter(|&y| mask.get_pixel(column, y)[0] > 0)
+        .collect();
+    let (top, bottom) = (covered[0], covered[covered.len() - 1]);
+    assert!(background.get_pixel(column, top)[0] > gray[0]);
+    assert!(background.get_pixel(column, bottom)[0] < gray[0]);
+    assert_eq!(background.get_pixel(0, 0), gray);
+}
+
+#[test]
+fn test_neon_glow() {
+    let black = Rgba([0, 0, 0, 255]);
+    let mut background = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(300, 100, black));
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 50., y: 50. }, Rgba([255, 240, 255, 255]));
+    let neon = NeonConfig {
+        glow_color: Rgba([255, 0, 200, 255]),
+        glow_radius: 6.,
+        glow_intensity: 2.,
+    };
+    let measurement = text_on_image_with_neon_glow(
+        &mut background,
+        "Neon",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        neon,
+    );
+    save_output(&background, "test_neon_glow.png").unwrap();
+    // The glow spreads past the text's measured area.
+    let glow_x = (measurement.left - 4) as u32;
+    let glow = background.get_pixel(glow_x, 50);
+    assert!(glow[0] > 0 && glow[1] == 0);
+    assert_eq!(background.get_pixel(0, 0), black);
+}
+
+#[test]
+fn test_measure_char() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([0, 0, 0, 255]));
+    let letter = font_bundle.measure_char('g');
+    assert!(letter.advance_width > 0.);
+    assert!(letter.max_x > letter.min_x);
+    // 'g' rises above the baseline and has a descender below it.
+    assert!(letter.min_y < 0. && letter.max_y > 0.);
+    let space = font_bundle.measure_char(' ');
+    assert!(space.advance_width > 0.);
+    assert_eq!(space.max_x - space.min_x, 0.);
+    assert_eq!(
+        font_bundle.measure_char('\u{10FFFD}'),
+        CharMetrics::default()
+    );
+}
+
+#[test]
+fn test_safe_clipping() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let mut background = DynamicImage::new_rgba8(100, 100);
+    let (_, clip_info) = text_on_image_safe(
+        &mut background,
+        "Fits\nFits",
+        &font_bundle,
+        50,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    assert_eq!(clip_info, ClipInfo::default());
+    // The second line runs off the right edge, far past the image for most of its characters.
+    let (_, clip_info) = text_on_image_safe(
+        &mut background,
+        "Fits\nThis line runs off the edge",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    assert_eq!(clip_info.lines_clipped, 1);
+    assert!(clip_info.chars_clipped > 10);
+}
+
+#[test]
+fn test_warp() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([0, 0, 0, 255]));
+    let mut background = DynamicImage::new_rgba8(300, 300);
+    let arc = text_on_image_with_warp(
+        &mut background,
+        "Around the circle",
+        &font_bundle,
+        WarpPath::Arc {
+            center_x: 150,
+            center_y: 150,
+            radius: 100.,
+            start_angle: -180.,
+        },
+        TextJustify::Left,
+        WrapBehavior::NoWrap,
+    );
+    save_output(&background, "test_warp_arc.png").unwrap();
+    // Starting on the left and running clockwise, the text passes over the top of the circle.
+    assert!(arc.left < 60 && arc.top < 60 && arc.bottom <= 155);
+    let mut background = DynamicImage::new_rgba8(300, 300);
+    let corners = [(50., 100.), (250., 60.), (250., 240.), (50., 200.)];
+    let perspective = text_on_image_with_warp(
+        &mut background,
+        "Tilted",
+        &font_bundle,
+        WarpPath::Perspective { corners },
+        TextJustify::Left,
+        WrapBehavior::NoWrap,
+    );
+    save_output(&background, "test_warp_perspective.png").unwrap();
+    assert!(perspective.left >= 49 && perspective.right <= 251);
+    assert!(perspective.top >= 59 && perspective.bottom <= 241);
+    assert!(perspective.width() > 150);
+}
+
+#[test]
+fn test_font_not_found() {
+    let err = load_font("assets/NoSuchFont.ttf").unwrap_err();
+    assert!(matches!(err, TextOnImageError::FontNotFound { .. }));
+    assert!(load_font("assets/BitstreamVeraSansMonoBold-pq1a.ttf").is_ok());
+    #[cfg(feature = "json")]
+    {
+        let mut background = DynamicImage::new_rgba8(100, 100);
+        let result = text_on_image_from_json(
+            &mut background,
+            r#"{
+                "text": "JSON",
+                "font_path": "assets/NoSuchFont.ttf",
+                "scale": 30.0,
+                "color": [0, 0, 0, 255],
+                "x": 50,
+                "y": 50
+            }"#,
+        );
+        assert!(matches!(
+            result,
+            Err(TextOnImageError::FontNotFound { name }) if name.contains("NoSuchFont")
+        ));
+    }
+}
+
+#[test]
+fn test_vertical_text() {
+    let mut background = DynamicImage::new_rgba8(100, 300);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([0, 0, 0, 255]));
+    let vertical_config = VerticalTextConfig {
+        char_spacing_px: 6,
+        rotation_deg: 0.,
+    };
+    let measurement = text_on_image_vertical(
+        &mut background,
+        "ABCDE",
+        &font_bundle,
+        50,
+        10,
+        VerticalAnchor::Top,
+        vertical_config,
+    );
+    save_output(&background, "test_vertical_text.png").unwrap();
+    assert_eq!(measurement.line_count, 1);
+    // Each character's rows of ink form a band below the previous character's band.
+    let inked_rows: Vec<u32> = (0..300)
+        .filter(|&y| (0..100).any(|x| background.get_pixel(x, y)[3] > 0))
+        .collect();
+    let mut bands: Vec<(u32, u32)> = Vec::new();
+    for y in inked_rows {
+        match bands.last_mut() {
+            Some((_, bottom)) if *bottom + 1 == y => *bottom = y,
+            _ => bands.push((y, y)),
+        }
+    }
+    assert_eq!(bands.len(), 5);
+    assert!(bands.windows(2).all(|pair| pair[0].1 < pair[1].0));
+    assert!(bands[4].1 < (measurement.top + measurement.height as i32) as u32);
+}
+
+#[cfg(feature = "rtl")]
+#[test]
+fn test_rtl() {
+    assert_eq!(reorder_rtl_line("שלום"), "םולש");
+    let mut background = DynamicImage::new_rgba8(400, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let measurement = text_on_image_rtl(
+        &mut background,
+        "שלום עולם",
+        &font_bundle,
+        380,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    // Left justified right-to-left text ends at the starting edge on the right.
+    assert_eq!(measurement.left + measurement.width as i32, 380);
+}
+
+#[test]
+fn test_gradient_shadow() {
+    let white = Rgba([255, 255, 255, 255]);
+    let mut background = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(300, 150, white));
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 50., y: 50. }, Rgba([200, 30, 30, 255]));
+    let shadow = GradientShadowConfig {
+        direction: ShadowDirection::Down,
+        length: 40,
+        color: Rgba([0, 0, 0, 255]),
+    };
+    let measurement = text_on_image_with_text_shadow_gradient(
+        &mut background,
+        "Cast",
+        &font_bundle,
+        150,
+        20,
+        TextJustify::Center,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        shadow,
+    );
+    save_output(&background, "test_gradient_shadow.png").unwrap();
+    // Below the text the shadow gets lighter the further down it reaches.
+    let bottom = (measurement.top + measurement.height as i32) as u32;
+    let column = (0..300)
+        .min_by_key(|&x| background.get_pixel(x, bottom)[0])
+        .unwrap();
+    let near = background.get_pixel(column, bottom);
+    let far = background.get_pixel(column, bottom + 20);
+    assert!(near[0] < far[0] && far[0] < 255);
+    assert_eq!(background.get_pixel(column, bottom + 60), white);
+}
+
+#[test]
+fn test_background_image() {
+    let texture = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(8, 8, |x, y| {
+        if (x + y) % 2 == 0 {
+            Rgba([150, 100, 50, 255])
+        } else {
+            Rgba([120, 80, 40, 255])
+        }
+    }));
+    let mut background = DynamicImage::new_rgba8(300, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([255, 255, 255, 255]));
+    let measurement = text_on_image_with_background_image(
+        &mut background,
+        "Wood grain",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        &texture,
+        1,
+        0,
+    );
+    save_output(&background, "test_background_image.png").unwrap();
+    // The texture tiles across the whole text area, starting one pixel into its pattern.
+    let (left, top) = (measurement.left as u32, measurement.top as u32);
+    assert_eq!(background.get_pixel(left, top), Rgba([120, 80, 40, 255]));
+    let (right, bottom) = (left + measurement.width - 1, top + measurement.height - 1);
+    assert_eq!(
+        background.get_pixel(right, bottom),
+        texture.get_pixel((1 + right - left) % 8, (bottom - top) % 8)
+    );
+    assert_eq!(background.get_pixel(left - 1, top)[3], 0);
+}
+
+#[test]
+fn test_monospace_grid() {
+    let mut background = DynamicImage::new_rgba8(400, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 16., y: 16. }, Rgba([0, 0, 0, 255]));
+    let config = MonospaceConfig {
+        cell_width: 12,
+        cell_height: 20,
+    };
+    let measurement = text_on_image_monospace(
+        &mut background,
+        "test ok\n\tpassed\nab\tc",
+        &font_bundle,
+        10,
+        10,
+        config,
+    );
+    save_output(&background, "test_monospace_grid.png").unwrap();
+    // The second row's tab pushes "passed" to column 8, the widest row at 14 columns.
+    assert_eq!(measurement.width, 14 * 12);
+    assert_eq!(measurement.height, 3 * 20);
+    assert_eq!(measurement.line_count, 3);
+    // Nothing is drawn in the cells the tab skipped over.
+    let tab_cells_empty =
+        (10..10 + 8 * 12).all(|x| (30..50).all(|y| background.get_pixel(x, y)[3] == 0));
+    assert!(tab_cells_empty);
+}
+
+#[test]
+fn test_ellipsize() {
+    let mut background = DynamicImage::new_rgba8(300, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let path = "/home/user/projects/text_on_image/src/lib.rs";
+    let mut ellipsize = |position| {
+        text_on_image_ellipsize(
+            &mut background,
+            path,
+            &font_bundle,
+            10,
+            50,
+            200,
+            "…",
+            position,
+            TextJustify::Left,
+            VerticalAnchor::Center,
+        )
+    };
+    let (start, measurement) = ellipsize(EllipsisPosition::Start);
+    assert!(measurement.width <= 200);
+    assert!(start.starts_with('…') && start.ends_with("lib.rs"));
+    let (middle, _) = ellipsize(EllipsisPosition::Middle);
+    assert!(middle.starts_with("/home") && middle.ends_with(".rs") && middle.contains('…'));
+    let (end, _) = ellipsize(EllipsisPosition::End);
+    assert!(end.starts_with("/home") && end.ends_with('…'));
+    let (short, _) = text_on_image_ellipsize(
+        &mut background,
+        "Short",
+        &font_bundle,
+        10,
+        50,
+        200,
+        "…",
+        EllipsisPosition::End,
+        TextJustify::Left,
+        VerticalAnchor::Center,
+    );
+    assert_eq!(short, "Short");
+}
+
+#[test]
+fn test_text_in_circle() {
+    let mut background = DynamicImage::new_rgba8(300, 300);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 18., y: 18. }, Rgba([0, 0, 0, 255]));
+    let region = CircleRegion {
+        center_x: 150,
+        center_y: 150,
+        radius: 100,
+    };
+    imageproc::drawing::draw_hollow_circle_mut(
+        &mut background,
+        (150, 150),
+        100,
+        Rgba([255, 0, 0, 255]),
+    );
+    let measurement = text_on_image_in_circle(
+        &mut background,
+        "Text that wraps to the shape of a circle, with short lines near the top and bottom and long lines through the middle.",
+        &font_bundle,
+        region,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+    );
+    save_output(&background, "test_text_in_circle.png").unwrap();
+    assert!(measurement.line_count > 3);
+    // Every pixel of text is inside the red circle.
+    let inside = background.pixels().all(|(x, y, pixel)| {
+        let (dx, dy) = (x as i32 - 150, y as i32 - 150);
+        pixel[3] == 0 || pixel[0] > 0 || dx * dx + dy * dy <= 100 * 100
+    });
+    assert!(inside);
+}
+
+#[test]
+fn test_word_wrap_callback() {
+    let mut background = DynamicImage::new_rgba8(400, 200);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    // Break before every word that starts with a capital letter.
+    let measurement = text_on_image_with_word_wrap_callback(
+        &mut background,
+        "One two Three four five Six",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        380,
+        |_, word| word.starts_with(char::is_uppercase),
+    );
+    assert_eq!(measurement.line_count, 3);
+    // The default logic written as a callback matches the built-in wrapping.
+    let text = "The default wrapping logic expressed as a callback";
+    let callback = text_on_image_with_word_wrap_callback(
+        &mut background,
+        text,
+        &font_bundle,
+        10,
+        100,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        200,
+        |line, word| get_text_width(&font_bundle, format!("{line} {word}")) > 200,
+    );
+    let built_in = measure_wrapped_text(
+        text,
+        &font_bundle,
+        10,
+        100,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::Wrap(200),
+    );
+    assert_eq!(callback.line_count, built_in.line_count);
+}
+
+#[cfg(feature = "hyphenation")]
+#[test]
+fn test_hyphenation_dict() {
+    use hyphenation::{Language, Load, Standard};
+    let dict = HyphenationDict::from(Standard::from_embedded(Language::EnglishUS).unwrap());
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let width = get_text_width(&font_bundle, "the hyphena");
+    let lines = hyphenated_lines("the hyphenation", &font_bundle, width, &dict);
+    // "hyphenation" can break as hy-phen-ation, and "the hyphen-" is the longest that fits.
+    assert_eq!(lines, vec!["the hyphen-", "ation"]);
+    let mut background = DynamicImage::new_rgba8(300, 100);
+    let measurement = text_on_image_with_hyphenation(
+        &mut background,
+        "the hyphenation",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        width,
+        &dict,
+    );
+    assert_eq!(measurement.line_count, 2);
+    assert!(matches!(
+        HyphenationDict::load(Language::EnglishUS, "assets/missing.bincode"),
+        Err(TextOnImageError::HyphenationError(_))
+    ));
+}
+
+#[test]
+fn test_line_numbers_margin() {
+    let mut background = DynamicImage::new_rgba8(400, 200);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 16., y: 16. }, Rgba([0, 0, 0, 255]));
+    let separator_color = Rgba([200, 200, 200, 255]);
+    let line_numbers = LineNumberConfig {
+        start: 9,
+        font_bundle: font_bundle.with_color_alpha(128),
+        gutter_width: 40,
+        separator_color: Some(separator_color),
+    };
+    let measurement = text_on_image_with_line_numbers_margin(
+        &mut background,
+        "fn main() {\n    println!(\"a long line that wraps onto a second row\");\n}",
+        &font_bundle,
+        10,
+        10,
+        Some(300),
+        line_numbers,
+    );
+    save_output(&background, "test_line_numbers_margin.png").unwrap();
+    assert_eq!(measurement.left, 10);
+    assert_eq!(measurement.line_count, 4);
+    assert!(measurement.width > 40);
+    assert_eq!(background.get_pixel(49, 10), separator_color);
+    // The wrapped row has no number of its own.
+    let row_height = measurement.height / 4;
+    let number_rows_inked = |row: u32| {
+        (10..48).any(|x| {
+            (10 + row * row_height..10 + (row + 1) * row_height)
+                .any(|y| background.get_pixel(x, y)[3] > 0)
+        })
+    };
+    assert!(number_rows_inked(0) && number_rows_inked(1) && number_rows_inked(3));
+    assert!(!number_rows_inked(2));
+}
+
+#[cfg(feature = "syntax")]
+#[test]
+fn test_syntax_highlighted() {
+    let mut background = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+        400,
+        100,
+        Rgba([43, 48, 59, 255]),
+    ));
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 16., y: 16. }, Rgba([0, 0, 0, 255]));
+    let measurement = text_on_image_syntax_highlighted(
+        &mut background,
+        "fn main() {\n\tlet answer = 42;\n}",
+        "rs",
+        "base16-ocean.dark",
+        &font_bundle,
+        10,
+        10,
+        4,
+    );
+    save_output(&background, "test_syntax_highlighted.png").unwrap();
+    assert_eq!(measurement.line_count, 3);
+    // Keywords, numbers and plain identifiers come out in several different colors.
+    let mut colors: Vec<Rgba<u8>> = background
+        .pixels()
+        .map(|(_, _, pixel)| pixel)
+        .filter(|pixel| pixel[0].max(pixel[1]).max(pixel[2]) > 150)
+        .collect();
+    colors.sort_by_key(|pixel| pixel.0);
+    colors.dedup();
+    assert!(colors.len() > 3);
+}
+
+#[test]
+fn test_wrap_at_chars_prefer_words() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let wrap = |text: &str, max_chars| {
+        wrap_text_to_lines(
+            text,
+            &font_bundle,
+            &WrapBehavior::WrapAtCharsPreferWords(max_chars),
+        )
+    };
+    // "efgh" starts exactly at the limit, so the break goes before it.
+    assert_eq!(wrap("abcd efgh", 5), vec!["abcd", "efgh"]);
+    // The space falls exactly on the limit, so the first four characters fill the line.
+    assert_eq!(wrap("abcd efgh", 4), vec!["abcd", "efgh"]);
+    assert_eq!(wrap("ab cdefgh ij", 6), vec!["ab", "cdefgh", "ij"]);
+    // A word longer than the limit breaks mid-word.
+    assert_eq!(wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+    let mut background = DynamicImage::new_rgba8(400, 200);
+    let measurement = text_on_image_with_max_chars_per_line(
+        &mut background,
+        "Translated subtitles often specify line lengths in characters",
+        &font_bundle,
+        200,
+        100,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        20,
+    );
+    assert_eq!(measurement.line_count, 4);
+}
+
+#[test]
+fn test_draw_debug_v2() {
+    let mut background = DynamicImage::new_rgba8(400, 200);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let info = text_on_image_draw_debug_v2(
+        &mut background,
+        "Debugging a layout that wraps",
+        &font_bundle,
+        200,
+        100,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::Wrap(200),
+    );
+    assert_eq!(info.anchor, (200, 100));
+    assert_eq!(info.wrapped_lines.len(), info.lines.len());
+    assert!(info.lines.len() > 1);
+    assert!(info.lines.windows(2).all(|pair| pair[0].top < pair[1].top));
+    assert!(info.bounding_box.top < 100 && info.bounding_box.bottom > 100);
+    let printed = info.to_string();
+    assert!(printed.starts_with("anchor: (200, 100)"));
+    assert!(printed.contains(&format!("{:?}", info.wrapped_lines[0])));
+}
+
+#[test]
+fn test_stroke_text() {
+    let mut background = DynamicImage::new_rgba8(300, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 60., y: 60. }, Rgba([0, 0, 0, 255]));
+    text_on_image_stroke_text(
+        &mut background,
+        "Hollow",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        3,
+    );
+    save_output(&background, "test_stroke_text.png").unwrap();
+    let (mask, _) = render_text_to_mask(
+        300,
+        100,
+        "Hollow",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    // Fully covered glyph pixels stay empty while pixels just outside the glyphs are stroked.
+    let interior_empty = mask
+        .enumerate_pixels()
+        .filter(|(_, _, coverage)| coverage[0] == 255)
+        .all(|(x, y, _)| background.get_pixel(x, y)[3] == 0);
+    assert!(interior_empty);
+    let stroked = mask
+        .enumerate_pixels()
+        .filter(|(_, _, coverage)| coverage[0] == 0)
+        .any(|(x, y, _)| background.get_pixel(x, y)[3] == 255);
+    assert!(stroked);
+}
+
+#[test]
+fn test_bevel() {
+    let mut background = DynamicImage::new_rgba8(300, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 80., y: 80. }, Rgba([128, 128, 128, 255]));
+    let light = Rgba([255, 255, 255, 255]);
+    let shadow = Rgba([0, 0, 0, 255]);
+    text_on_image_bevel(
+        &mut background,
+        "HI",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        BevelConfig {
+            light_color: light,
+            shadow_color: shadow,
+            depth: 4,
+        },
+    );
+    save_output(&background, "test_bevel.png").unwrap();
+    let (mask, _) = render_text_to_mask(
+        300,
+        100,
+        "HI",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    // The topmost covered pixel of the glyphs is lit and the bottommost is shaded.
+    let (top_x, top_y, _) = mask
+        .enumerate_pixels()
+        .find(|(_, _, c)| c[0] == 255)
+        .unwrap();
+    let (bottom_x, bottom_y, _) = mask
+        .enumerate_pixels()
+        .filter(|(_, _, c)| c[0] == 255)
+        .last()
+        .unwrap();
+    assert_eq!(background.get_pixel(top_x, top_y), light);
+    assert_eq!(background.get_pixel(bottom_x, bottom_y), shadow);
+    let has_face = background
+        .pixels()
+        .any(|(_, _, pixel)| pixel == Rgba([128, 128, 128, 255]));
+    assert!(has_face);
+}
+
+#[test]
+fn test_frosted_glass() {
+    // Stripes that a blur evens out.
+    let mut background = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(300, 100, |x, _| {
+        if x % 4 < 2 {
+            Rgba([255, 0, 0, 255])
+        } else {
+            Rgba([0, 0, 255, 255])
+        }
+    }));
+    let original = background.clone();
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([255, 255, 255, 255]));
+    let measurement = text_on_image_frosted_glass(
+        &mut background,
+        "Glass",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        FrostedGlassConfig {
+            blur_radius: 3.,
+            tint: Some(Rgba([255, 255, 255, 64])),
+            opacity: 255,
+        },
+    );
+    save_output(&background, "test_frosted_glass.png").unwrap();
+    // Outside the text area nothing changes, and the backdrop's corner is blurred and tinted.
+    assert_eq!(background.get_pixel(0, 0), original.get_pixel(0, 0));
+    let corner = background.get_pixel(measurement.left as u32, measurement.top as u32);
+    assert!(corner[0] > 100 && corner[0] < 255);
+    assert!(corner[1] > 0);
+    assert!(corner[2] > 100 && corner[2] < 255);
+}
+
+#[test]
+fn test_with_cursor() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([0, 0, 0, 255]));
+    let cursor_color = Rgba([255, 0, 0, 255]);
+    let draw = |blink_visible: bool| {
+        let mut background = DynamicImage::new_rgba8(300, 100);
+        text_on_image_with_cursor(
+            &mut background,
+            "Hello",
+            &font_bundle,
+            10,
+            10,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+            CursorConfig {
+                char_index: 2,
+                color: cursor_color,
+                width: 2,
+                blink_visible,
+            },
+        );
+        background
+    };
+    let background = draw(true);
+    save_output(&background, "test_with_cursor.png").unwrap();
+    let cursor_x = 10 + get_char_positions(&font_bundle, "Hello")[2];
+    assert_eq!(background.get_pixel(cursor_x, 12), cursor_color);
+    assert_eq!(background.get_pixel(cursor_x + 1, 40), cursor_color);
+    assert_ne!(background.get_pixel(cursor_x + 2, 12), cursor_color);
+    let hidden = draw(false);
+    assert!(!hidden.pixels().any(|(_, _, pixel)| pixel == cursor_color));
+}
+
+#[test]
+fn test_cursor_on_justified_line() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let cursor_color = Rgba([255, 0, 0, 255]);
+    let text = "one two three four five six seven";
+    let wrap_behavior = WrapBehavior::Wrap(200);
+    let lines = wrap_text_to_lines(text, &font_bundle, &wrap_behavior);
+    assert!(lines.len() > 1);
+    // The start of the last word on the first line, which stretching moves furthest.
+    let char_index = lines[0].rfind(' ').unwrap() + 1;
+    let mut word_x = None;
+    text_on_image_with_glyph_callback(
+        &mut DynamicImage::new_rgba8(300, 100),
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::JustifyLastLeft,
+        VerticalAnchor::Top,
+        wrap_behavior,
+        |_, glyph| {
+            if glyph.line_index == 0 && glyph.char_index == char_index {
+                word_x = Some(glyph.draw_x as u32);
+            }
+        },
+    );
+    let word_x = word_x.unwrap();
+    assert!(word_x > 10 + get_char_positions(&font_bundle, &lines[0])[char_index]);
+    let mut background = DynamicImage::new_rgba8(300, 100);
+    text_on_image_with_cursor(
+        &mut background,
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::JustifyLastLeft,
+        VerticalAnchor::Top,
+        wrap_behavior,
+        CursorConfig {
+            char_index,
+            color: cursor_color,
+            width: 1,
+            blink_visible: true,
+        },
+    );
+    save_output(&background, "test_cursor_on_justified_line.png").unwrap();
+    assert_eq!(background.get_pixel(word_x, 12), cursor_color);
+    assert_ne!(background.get_pixel(word_x - 1, 12), cursor_color);
+}
+
+#[test]
+fn test_cursor_indexes_source_text() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let cursor_color = Rgba([255, 0, 0, 255]);
+    let line_height = get_text_height(&font_bundle) as u32;
+    // The top left corner of the drawn cursor, if any.
+    let cursor_at = |text: &str, wrap_behavior, char_index| {
+        let mut background = DynamicImage::new_rgba8(300, 200);
+        text_on_image_with_cursor(
+            &mut background,
+            text,
+            &font_bundle,
+            10,
+            10,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            wrap_behavior,
+            CursorConfig {
+                char_index,
+                color: cursor_color,
+                width: 1,
+                blink_visible: true,
+            },
+        );
+        background
+            .pixels()
+            .filter(|&(_, _, pixel)| pixel == cursor_color)
+            .map(|(x, y, _)| (x, y))
+            .min()
+    };
+
+    // The space dropped at the wrap still counts.
+    let wrap_behavior = WrapBehavior::Wrap(get_text_width(&font_bundle, "ab"));
+    let cd = get_char_positions(&font_bundle, "cd");
+    assert_eq!(
+        cursor_at("ab cd", wrap_behavior, 4),
+        Some((10 + cd[1], 10 + line_height))
+    );
+    assert_eq!(
+        cursor_at("ab cd", wrap_behavior, 2),
+        Some((10, 10 + line_height))
+    );
+    assert_eq!(
+        cursor_at("ab cd", wrap_behavior, 5),
+        Some((10 + cd[2], 10 + line_height))
+    );
+
+    // Hyphens added by wrapping do not.
+    let long_word = "abcdefghijklmnopqrstuvwxyz";
+    let hyphenated = wrap_text_to_lines(long_word, &font_bundle, &WrapBehavior::Wrap(100));
+    let last_line = hyphenated.last().unwrap();
+    let last_positions = get_char_positions(&font_bundle, last_line);
+    assert_eq!(
+        cursor_at(long_word, WrapBehavior::Wrap(100), 25),
+        Some((
+            10 + last_positions[last_line.chars().count() - 1],
+            10 + line_height * (hyphenated.len() as u32 - 1)
+        ))
+    );
+
+    // Empty text still shows where typing would start.
+    assert_eq!(cursor_at("", WrapBehavior::NoWrap, 0), Some((10, 10)));
+}
+
+#[test]
+fn test_wrap_strict_no_hyphen() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let max_width = get_text_width(&font_bundle, "Antidisestablishment");
+    let mut background = DynamicImage::new_rgba8(400, 200);
+    let measurement = text_on_image_try(
+        &mut background,
+        "the quick brown fox jumps over the lazy dog",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::WrapStrictNoHyphen(max_width),
+    )
+    .unwrap();
+    save_output(&background, "test_wrap_strict_no_hyphen.png").unwrap();
+    let lines = wrap_text_to_lines(
+        "the quick brown fox jumps over the lazy dog",
+        &font_bundle,
+        &WrapBehavior::WrapStrictNoHyphen(max_width),
+    );
+    assert_eq!(measurement.line_count, lines.len());
+    assert!(lines.iter().all(|line| !line.contains('-')));
+    assert!(lines
+        .iter()
+        .all(|line| get_text_width(&font_bundle, line) <= max_width));
+
+    let mut untouched = DynamicImage::new_rgba8(400, 200);
+    let result = text_on_image_wrapped_with_hard_max(
+        &mut untouched,
+        "short Antidisestablishmentarianism",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        max_width,
+    );
+    match result {
+        Err(TextOnImageError::WordTooWide {
+            word,
+            width,
+            max_width: reported_max,
+        }) => {
+            assert_eq!(word, "Antidisestablishmentarianism");
+            assert!(width > max_width);
+            assert_eq!(reported_max, max_width);
+        }
+        other => panic!("expected WordTooWide, got {:?}", other),
+    }
+    assert!(untouched.pixels().all(|(_, _, pixel)| pixel[3] == 0));
+
+    // Settings that text_on_image panics on are errors too.
+    for wrap_behavior in [
+        WrapBehavior::Wrap(5),
+        WrapBehavior::WrapOptimal(5),
+        WrapBehavior::WrapAtCharsPreferWords(0),
+    ] {
+        let result = text_on_image_try(
+            &mut untouched,
+            "short words",
+            &font_bundle,
+            10,
+            10,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            wrap_behavior,
+        );
+        assert!(
+            matches!(result, Err(TextOnImageError::InvalidWrap(invalid)) if invalid == wrap_behavior)
+        );
+    }
+    assert!(untouched.pixels().all(|(_, _, pixel)| pixel[3] == 0));
+}
+
+#[test]
+fn test_with_line_indent_fn() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let text = "The first paragraph runs long enough to wrap onto more lines.\nThe second one too, with a few more words.";
+    let mut background = DynamicImage::new_rgba8(300, 300);
+    let measurement = text_on_image_with_line_indent_fn(
+        &mut background,
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::Wrap(200),
+        |_, is_paragraph_first| if is_paragraph_first { 40 } else { 0 },
+    );
+    save_output(&background, "test_with_line_indent_fn.png").unwrap();
+    assert!(measurement.line_count >= 4);
+    assert!(measurement.width <= 200);
+    let line_height = (measurement.height / measurement.line_count as u32) as i32;
+    let row_left = |line: i32| {
+        let (top, bottom) = (10 + line * line_height, 10 + (line + 1) * line_height);
+        background
+            .pixels()
+            .filter(|&(_, y, pixel)| pixel[3] > 0 && (y as i32) >= top && (y as i32) < bottom)
+            .map(|(x, _, _)| x)
+            .min()
+            .unwrap()
+    };
+    // The first line is indented and the continuation line after it is not.
+    assert!(row_left(0) >= 50);
+    assert!(row_left(1) < 20);
+}
+
+#[test]
+fn test_with_shadow_multiple() {
+    let mut background = DynamicImage::new_rgba8(300, 100);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([255, 255, 255, 255]));
+    let sharp = Rgba([255, 0, 0, 255]);
+    let diffuse = Rgba([0, 0, 255, 255]);
+    let shadows = [
+        ShadowConfig {
+            offset_x: 3,
+            offset_y: 3,
+            color: sharp,
+            blur_radius: None,
+        },
+        ShadowConfig {
+            offset_x: 6,
+            offset_y: 6,
+            color: diffuse,
+            blur_radius: Some(3.),
+        },
+    ];
+    let measurement = text_on_image_with_shadow_multiple(
+        &mut background,
+        "Stack",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        &shadows,
+    );
+    save_output(&background, "test_with_shadow_multiple.png").unwrap();
+    let mut plain = DynamicImage::new_rgba8(300, 100);
+    let plain_measurement = text_on_image(
+        &mut plain,
+        "Stack",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    assert_eq!(measurement, plain_measurement);
+    // The sharp shadow sits over the diffuse one, and the blurred one spreads into soft edges.
+    assert!(background.pixels().any(|(_, _, pixel)| pixel == sharp));
+    assert!(background
+        .pixels()
+        .any(|(_, _, pixel)| pixel[2] > 0 && pixel[3] > 0 && pixel[3] < 255));
+    // Fully covered text pixels stay the text color.
+    for (x, y, pixel) in plain.pixels() {
+        if pixel[3] == 255 {
+            assert_eq!(background.get_pixel(x, y), Rgba([255, 255, 255, 255]));
+        }
+    }
+}
+
+#[test]
+fn test_marquee() {
+    let base = DynamicImage::new_rgba8(200, 60);
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([255, 255, 255, 255]));
+    let frames = text_on_image_marquee(
+        &base,
+        "News",
+        &font_bundle,
+        MarqueeConfig {
+            frame_count: 10,
+            scroll_speed_px_per_frame: 25,
+            direction: ScrollDirection::RightToLeft,
+            background: Rgba([0, 0, 0, 255]),
+        },
+    );
+    assert_eq!(frames.len(), 10);
+    for (index, frame) in frames.iter().enumerate() {
+        save_output(frame, &format!("test_marquee_{}.png", index)).unwrap();
+    }
+    let leftmost_text = |frame: &DynamicImage| {
+        frame
+            .pixels()
+            .filter(|(_, _, pixel)| pixel[0] > 0)
+            .map(|(x, _, _)| x)
+            .min()
+    };
+    // The first frame has the text just off the right edge, then it moves left every frame.
+    assert_eq!(leftmost_text(&frames[0]), None);
+    assert_eq!(frames[0].get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+    let second = leftmost_text(&frames[2]).unwrap();
+    let third = leftmost_text(&frames[3]).unwrap();
+    assert!(third < second);
+    assert!(base.pixels().all(|(_, _, pixel)| pixel[3] == 0));
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_with_jitter() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([0, 0, 0, 255]));
+    let draw = |seed: u64, max_offset: f32, max_rotation_deg: f32| {
+        let mut background = DynamicImage::new_rgba8(300, 100);
+        text_on_image_with_jitter(
+            &mut background,
+            "Handwritten",
+            &font_bundle,
+            150,
+            50,
+            TextJustify::Center,
+            VerticalAnchor::Center,
+            WrapBehavior::NoWrap,
+            JitterConfig {
+                max_x_offset: max_offset,
+                max_y_offset: max_offset,
+                max_rotation_deg,
+                seed,
+            },
+        );
+        background
+    };
+    let jittered = draw(7, 3., 10.);
+    save_output(&jittered, "test_with_jitter.png").unwrap();
+    assert_eq!(jittered.to_rgba8(), draw(7, 3., 10.).to_rgba8());
+    assert_ne!(jittered.to_rgba8(), draw(8, 3., 10.).to_rgba8());
+    assert!(jittered.pixels().any(|(_, _, pixel)| pixel[3] > 0));
+    // With no jitter allowed, every seed draws the same text.
+    assert_eq!(draw(1, 0., 0.).to_rgba8(), draw(2, 0., 0.).to_rgba8());
+}
+
+#[cfg(feature = "svg")]
+#[test]
+fn test_text_to_svg() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([255, 0, 0, 128]));
+    let single = text_to_svg(
+        "Fish & <Chips>",
+        &font_bundle,
+        100,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    assert!(single.starts_with("<text x=\"100\""));
+    assert!(single.contains("text-anchor=\"middle\""));
+    assert!(single.contains("fill=\"#ff0000\""));
+    assert!(single.contains("fill-opacity=\"0.502\""));
+    assert!(single.contains(">Fish &amp; &lt;Chips&gt;</text>"));
+    assert!(!single.contains("<tspan"));
+
+    let wrapped = text_to_svg(
+        "one two three four five six",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::Wrap(100),
+    );
+    let line_count = wrap_text_to_lines(
+        "one two three four five six",
+        &font_bundle,
+        &WrapBehavior::Wrap(100),
+    )
+    .len();
+    assert!(line_count > 1);
+    assert_eq!(wrapped.matches("<tspan").count(), line_count);
+    assert_eq!(wrapped.matches(" dy=\"").count(), line_count - 1);
+    assert!(wrapped.contains("text-anchor=\"start\""));
+
+    let document = svg_document(200, 100, Some(("Vera", FONT)), &single);
+    std::fs::create_dir_all("./output").unwrap();
+    std::fs::write("./output/test_text_to_svg.svg", &document).unwrap();
+    assert!(document.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(document.contains("src:url(data:font/ttf;base64,"));
+    assert!(document.ends_with("</text></svg>"));
+}
+
+#[test]
+fn test_with_font_fallback() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let primary = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([255, 0, 0, 255]));
+    let fallback = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([0, 0, 255, 255]));
+    let mut background = DynamicImage::new_rgba8(300, 100);
+    let measurement = text_on_image_with_font_fallback(
+        &mut background,
+        "Fallback 日本",
+        &[&primary, &fallback],
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    save_output(&background, "test_with_font_fallback.png").unwrap();
+    // Characters the first font covers are drawn with it, and nothing falls through to the
+    // second font when it has no more glyphs than the first.
+    let mut plain = DynamicImage::new_rgba8(300, 100);
+    let plain_measurement = text_on_image(
+        &mut plain,
+        "Fallback 日本",
+        &primary,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    assert_eq!(measurement, plain_measurement);
+    assert!(background.pixels().any(|(_, _, pixel)| pixel[0] > 0));
+    assert!(background.pixels().all(|(_, _, pixel)| pixel[2] == 0));
+}
+
+#[test]
+fn test_outline_only() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 60., y: 60. }, Rgba([0, 0, 0, 255]));
+    let mut outlined = DynamicImage::new_rgba8(300, 100);
+    text_on_image_outline_only(
+        &mut outlined,
+        "Stencil",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        2,
+    );
+    save_output(&outlined, "test_outline_only.png").unwrap();
+    let mut stroked = DynamicImage::new_rgba8(300, 100);
+    text_on_image_stroke_text(
+        &mut stroked,
+        "Stencil",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        2,
+    );
+    assert!(outlined.pixels().any(|(_, _, pixel)| pixel[3] > 0));
+    assert_eq!(outlined.to_rgba8(), stroked.to_rgba8());
+}
+
+#[test]
+fn test_with_slant() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 60., y: 60. }, Rgba([0, 0, 0, 255]));
+    let mut background = DynamicImage::new_rgba8(300, 100);
+    text_on_image_with_slant(
+        &mut background,
+        "|",
+        &font_bundle,
+        150,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        0.3,
+    );
+    save_output(&background, "test_with_slant.png").unwrap();
+    let row_center = |image: &DynamicImage, y: u32| {
+        let columns: Vec<u32> = (0..image.width())
+            .filter(|&x| image.get_pixel(x, y)[3] > 128)
+            .collect();
+        (columns[0] + columns[columns.len() - 1]) / 2
+    };
+    let covered_rows: Vec<u32> = (0..100)
+        .filter(|&y| (0..300).any(|x| background.get_pixel(x, y)[3] > 128))
+        .collect();
+    let (top, bottom) = (covered_rows[2], covered_rows[covered_rows.len() - 3]);
+    // The top of the upright stroke has moved right of its bottom.
+    assert!(row_center(&background, top) as f32 - row_center(&background, bottom) as f32 > 8.);
+}
+
+#[test]
+fn test_with_condensed() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([0, 0, 0, 255]));
+    let mut background = DynamicImage::new_rgba8(300, 100);
+    let condensed = text_on_image_with_condensed(
+        &mut background,
+        "Condensed",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        0.8,
+    );
+    save_output(&background, "test_with_condensed.png").unwrap();
+    let normal = measure_wrapped_text(
+        "Condensed",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    assert_eq!(condensed.height, normal.height);
+    let ratio = condensed.width as f32 / normal.width as f32;
+    assert!((ratio - 0.8).abs() < 0.02, "ratio was {}", ratio);
+}
+
+#[test]
+fn test_with_extended() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([0, 0, 0, 255]));
+    let draw = |extend_factor: f32, wrap_behavior: WrapBehavior| {
+        let mut background = DynamicImage::new_rgba8(400, 200);
+        let measurement = text_on_image_with_extended(
+            &mut background,
+            "Extended text",
+            &font_bundle,
+            10,
+            10,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            wrap_behavior,
+            extend_factor,
+        );
+        (background, measurement)
+    };
+    let (_, normal) = draw(1., WrapBehavior::NoWrap);
+    let (background, extended) = draw(1.5, WrapBehavior::NoWrap);
+    save_output(&background, "test_with_extended.png").unwrap();
+    assert_eq!(
+        normal.width,
+        get_text_width(&font_bundle, "Extended text"),
+        "a factor of 1.0 changes nothing"
+    );
+    let ratio = extended.width as f32 / normal.width as f32;
+    assert!((ratio - 1.5).abs() < 0.02, "ratio was {}", ratio);
+    // A width that holds the text at normal scale is too narrow once it is extended.
+    let (_, wrapped) = draw(1.5, WrapBehavior::Wrap(normal.width));
+    assert_eq!(draw(1., WrapBehavior::Wrap(normal.width)).1.line_count, 1);
+    assert!(wrapped.line_count > 1);
+    assert!(wrapped.width <= normal.width);
+}
+
+#[test]
+fn test_with_smart_quotes() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 24., y: 24. }, Rgba([0, 0, 0, 255]));
+    let draw = |text: &str, smart: bool| {
+        let mut background = DynamicImage::new_rgba8(500, 60);
+        if smart {
+            text_on_image_with_smart_quotes(
+                &mut background,
+                text,
+                &font_bundle,
+                10,
+                10,
+                TextJustify::Left,
+                VerticalAnchor::Top,
+                WrapBehavior::NoWrap,
+            );
+        } else {
+            text_on_image(
+                &mut background,
+                text,
+                &font_bundle,
+                10,
+                10,
+                TextJustify::Left,
+                VerticalAnchor::Top,
+                WrapBehavior::NoWrap,
+            );
+        }
+        background
+    };
+    let smart = draw("\"It's (\"quoted\") -- wait...\" 'ok'", true);
+    save_output(&smart, "test_with_smart_quotes.png").unwrap();
+    let expected = draw("\u{201C}It\u{2019}s (\u{201C}quoted\u{201D}) \u{2014} wait\u{2026}\u{201D} \u{2018}ok\u{2019}", false);
+    assert!(
+        check_font_has_glyphs(&font, "\u{201C}\u{201D}\u{2018}\u{2019}\u{2014}\u{2026}").is_empty()
+    );
+    assert_eq!(smart.to_rgba8(), expected.to_rgba8());
+}
+
+#[test]
+fn test_with_small_caps() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([0, 0, 0, 255]));
+    let mut background = DynamicImage::new_rgba8(400, 100);
+    let measurement = text_on_image_with_small_caps(
+        &mut background,
+        "Small Caps",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    save_output(&background, "test_with_small_caps.png").unwrap();
+    let small_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([0, 0, 0, 255]));
+    let expected_width = get_text_width(&font_bundle, "S")
+        + get_text_width(&small_bundle, "MALL")
+        + get_text_width(&font_bundle, " C")
+        + get_text_width(&small_bundle, "APS");
+    assert_eq!(measurement.width, expected_width);
+    assert_eq!(
+        measurement.height,
+        measure_text_height_for_lines(1, &font_bundle)
+    );
+    // Every glyph sits on the same baseline, so the small letters end where the capitals do.
+    let lowest_row = |x_range: std::ops::Range<u32>| {
+        (0..100)
+            .filter(|&y| x_range.clone().any(|x| background.get_pixel(x, y)[3] > 128))
+            .max()
+            .unwrap()
+    };
+    let capital_width = get_text_width(&font_bundle, "S");
+    assert_eq!(
+        lowest_row(10..10 + capital_width),
+        lowest_row(10 + capital_width..10 + capital_width * 2)
+    );
+}
+
+#[test]
+fn test_number_formatted() {
+    assert_eq!(format_number(1234567.891, 2, ',', '.'), "1,234,567.89");
+    assert_eq!(format_number(-1234.5, 1, '.', ','), "-1.234,5");
+    assert_eq!(format_number(999.999, 2, ',', '.'), "1,000.00");
+    assert_eq!(format_number(12., 0, ' ', '.'), "12");
+    assert_eq!(format_number(-0.001, 2, ',', '.'), "0.00");
+
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([0, 0, 0, 255]));
+    let symbol_bundle = FontBundle::new(&font, Scale { x: 18., y: 18. }, Rgba([0, 0, 0, 255]));
+    let mut background = DynamicImage::new_rgba8(400, 100);
+    let number = text_on_image_number_formatted(
+        &mut background,
+        1234567.891,
+        2,
+        ',',
+        '.',
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+    );
+    assert_eq!(number.width, get_text_width(&font_bundle, "1,234,567.89"));
+    let currency = text_on_image_currency(
+        &mut background,
+        1234.5,
+        "$",
+        &font_bundle,
+        &symbol_bundle,
+        10,
+        50,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        false,
+    );
+    save_output(&background, "test_number_formatted.png").unwrap();
+    assert_eq!(
+        currency.width,
+        get_text_width(&symbol_bundle, "$") + get_text_width(&font_bundle, "1,234.50")
+    );
+    assert_eq!(
+        currency.height,
+        measure_text_height_for_lines(1, &font_bundle)
+    );
+}
+
+#[test]
+fn test_with_placeholder() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 24., y: 24. }, Rgba([0, 0, 0, 255]));
+    let values = std::collections::HashMap::from([
+        ("name".to_string(), "Ada".to_string()),
+        ("day".to_string(), "Monday".to_string()),
+    ]);
+    let template = "Hello, {name}! See you {day}, {title}.";
+    let draw = |policy: MissingKeyPolicy| {
+        let mut background = DynamicImage::new_rgba8(500, 60);
+        let result = text_on_image_with_placeholder(
+            &mut background,
+            template,
+            &values,
+            policy,
+            &font_bundle,
+            10,
+            10,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+        );
+        (background, result)
+    };
+    let (background, result) = draw(MissingKeyPolicy::Leave);
+    save_output(&background, "test_with_placeholder.png").unwrap();
+    let (substituted, measurement) = result.unwrap();
+    assert_eq!(substituted, vec!["name".to_string(), "day".to_string()]);
+    assert_eq!(
+        measurement.width,
+        get_text_width(&font_bundle, "Hello, Ada! See you Monday, {title}.")
+    );
+    let (_, result) = draw(MissingKeyPolicy::ReplaceWithEmpty);
+    assert_eq!(
+        result.unwrap().1.width,
+        get_text_width(&font_bundle, "Hello, Ada! See you Monday, .")
+    );
+    let (untouched, result) = draw(MissingKeyPolicy::Error);
+    match result {
+        Err(TextOnImageError::MissingPlaceholder { key }) => assert_eq!(key, "title"),
+        other => panic!("expected MissingPlaceholder, got {:?}", other),
+    }
+    assert!(untouched.pixels().all(|(_, _, pixel)| pixel[3] == 0));
+}
+
+#[test]
+fn test_insert_auto_newlines() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let text = "Stored text gets its line breaks ahead of time.\nEven across paragraphs.";
+    let wrapped = insert_auto_newlines(text, &font_bundle, 150);
+    assert!(wrapped.lines().count() > 2);
+    assert!(wrapped
+        .lines()
+        .all(|line| get_text_width(&font_bundle, line) <= 150));
+    let draw = |text: &str, wrap_behavior: WrapBehavior| {
+        let mut background = DynamicImage::new_rgba8(200, 200);
+        text_on_image(
+            &mut background,
+            text,
+            &font_bundle,
+            10,
+            10,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            wrap_behavior,
+        );
+        background
+    };
+    let stored = draw(&wrapped, WrapBehavior::NoWrap);
+    save_output(&stored, "test_insert_auto_newlines.png").unwrap();
+    assert_eq!(
+        stored.to_rgba8(),
+        draw(text, WrapBehavior::Wrap(150)).to_rgba8()
+    );
+}
+
+#[test]
+fn test_with_color_per_line() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let red = Rgba([255, 0, 0, 255]);
+    let blue = Rgba([0, 0, 255, 255]);
+    let mut background = DynamicImage::new_rgba8(200, 120);
+    let measurement = text_on_image_with_color_per_line(
+        &mut background,
+        "Monday\nTuesday\nWednesday",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        &[red, blue],
+    );
+    save_output(&background, "test_with_color_per_line.png").unwrap();
+    assert_eq!(measurement.line_count, 3);
+    let line_height = measurement.height / 3;
+    let colors_in_line = |line: u32| {
+        let (top, bottom) = (10 + line * line_height, 10 + (line + 1) * line_height);
+        let mut colors: Vec<Rgba<u8>> = background
+            .pixels()
+            .filter(|&(_, y, pixel)| pixel[3] == 255 && y >= top && y < bottom)
+            .map(|(_, _, pixel)| pixel)
+            .collect();
+        colors.dedup();
+        colors
+    };
+    assert_eq!(colors_in_line(0), vec![red]);
+    assert_eq!(colors_in_line(1), vec![blue]);
+    assert_eq!(colors_in_line(2), vec![red]);
+    assert_eq!(font_bundle.with_color(blue).color(), blue);
+    assert_eq!(font_bundle.color(), Rgba([0, 0, 0, 255]));
+}
+
+#[test]
+fn test_with_spaced_lines() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let text = "Header\nFirst item\nSecond item";
+    let draw = |extra_spacing: &[(usize, u32)]| {
+        let mut background = DynamicImage::new_rgba8(200, 200);
+        let measurement = text_on_image_with_spaced_lines(
+            &mut background,
+            text,
+            &font_bundle,
+            10,
+            100,
+            TextJustify::Left,
+            VerticalAnchor::Center,
+            WrapBehavior::NoWrap,
+            extra_spacing,
+        );
+        (background, measurement)
+    };
+    let (plain, plain_measurement) = draw(&[]);
+    let (spaced, spaced_measurement) = draw(&[(0, 16), (2, 50)]);
+    save_output(&spaced, "test_with_spaced_lines.png").unwrap();
+    assert_eq!(plain_measurement.height + 16, spaced_measurement.height);
+    assert_eq!(plain_measurement.top - 8, spaced_measurement.top);
+    let line_height = plain_measurement.height as i32 / 3;
+    // The header moves up half the gap and the lines after it move down the other half.
+    let shifted = |from: &DynamicImage, to: &DynamicImage, line: i32, shift: i32| {
+        let top = plain_measurement.top + line * line_height;
+        (top..top + line_height).all(|y| {
+            (0..200).all(|x| from.get_pixel(x, y as u32) == to.get_pixel(x, (y + shift) as u32))
+        })
+    };
+    assert!(shifted(&plain, &spaced, 0, -8));
+    assert!(shifted(&plain, &spaced, 1, 8));
+    assert!(shifted(&plain, &spaced, 2, 8));
+}
+
+#[test]
+fn test_with_baseline_shifts() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([0, 0, 0, 255]));
+    let mut background = DynamicImage::new_rgba8(200, 100);
+    text_on_image_with_baseline_shifts(
+        &mut background,
+        "x2x",
+        &font_bundle,
+        10,
+        30,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        &[
+            BaselineShift {
+                start_char: 1,
+                end_char: 2,
+                shift_px: -4,
+            },
+            BaselineShift {
+                start_char: 1,
+                end_char: 3,
+                shift_px: -10,
+            },
+        ],
+    );
+    save_output(&background, "test_with_baseline_shifts.png").unwrap();
+    let char_positions = get_char_positions(&font_bundle, "x2x");
+    let lowest_row = |char_index: usize| {
+        let columns = 10 + char_positions[char_index]..10 + char_positions[char_index + 1];
+        (0..100)
+            .filter(|&y| columns.clone().any(|x| background.get_pixel(x, y)[3] > 128))
+            .max()
+            .unwrap() as i32
+    };
+    // The last range wins where they overlap, and its negative shift raises both shifted
+    // characters by 10 pixels.
+    assert_eq!(lowest_row(0) - lowest_row(2), 10);
+    assert!(lowest_row(0) - lowest_row(1) >= 9);
+
+    // Unshifted glyphs land where text_on_image draws them, including on stretched lines.
+    let text = "one two three four five six seven";
+    let mut shifted = DynamicImage::new_rgba8(300, 100);
+    text_on_image_with_baseline_shifts(
+        &mut shifted,
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::JustifyLastLeft,
+        VerticalAnchor::Top,
+        WrapBehavior::Wrap(200),
+        &[],
+    );
+    let mut plain = DynamicImage::new_rgba8(300, 100);
+    text_on_image(
+        &mut plain,
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::JustifyLastLeft,
+        VerticalAnchor::Top,
+        WrapBehavior::Wrap(200),
+    );
+    // The two ways of drawing glyphs round coverage slightly differently.
+    assert!(shifted
+        .pixels()
+        .zip(plain.pixels())
+        .all(|((_, _, a), (_, _, b))| a[3].abs_diff(b[3]) <= 2));
+
+    // Ranges index the text itself, so the space dropped at the wrap still counts.
+    let wrap_behavior = WrapBehavior::Wrap(get_text_width(&font_bundle, "ab"));
+    assert_eq!(
+        wrap_text_to_lines("ab cd", &font_bundle, &wrap_behavior),
+        ["ab", "cd"]
+    );
+    let mut wrapped = DynamicImage::new_rgba8(200, 100);
+    text_on_image_with_baseline_shifts(
+        &mut wrapped,
+        "ab cd",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        wrap_behavior,
+        &[BaselineShift {
+            start_char: 3,
+            end_char: 4,
+            shift_px: -10,
+        }],
+    );
+    let char_positions = get_char_positions(&font_bundle, "cd");
+    let lowest_row = |char_index: usize| {
+        let columns = 10 + char_positions[char_index]..10 + char_positions[char_index + 1];
+        (0..100)
+            .filter(|&y| columns.clone().any(|x| wrapped.get_pixel(x, y)[3] > 128))
+            .max()
+            .unwrap() as i32
+    };
+    assert_eq!(lowest_row(1) - lowest_row(0), 10);
+}
+
+#[test]
+fn test_with_word_spacing() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let draw = |text: &str, extra_space_px: f32, wrap_behavior: WrapBehavior| {
+        let mut background = DynamicImage::new_rgba8(400, 100);
+        let measurement = text_on_image_with_word_spacing(
+            &mut background,
+            text,
+            &font_bundle,
+            10,
+            10,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            wrap_behavior,
+            extra_space_px,
+        );
+        (background, measurement)
+    };
+    let (_, normal) = draw("three short words", 0., WrapBehavior::NoWrap);
+    let (wide_image, wide) = draw("three short words", 10., WrapBehavior::NoWrap);
+    let (_, narrow) = draw("three short words", -4., WrapBehavior::NoWrap);
+    save_output(&wide_image, "test_with_word_spacing.png").unwrap();
+    assert_eq!(
+        normal.width,
+        get_text_width(&font_bundle, "three short words")
+    );
+    assert_eq!(wide.width, normal.width + 20);
+    assert_eq!(narrow.width, normal.width - 8);
+    // Words without spaces are untouched.
+    assert_eq!(
+        draw("word", 10., WrapBehavior::NoWrap).1.width,
+        get_text_width(&font_bundle, "word")
+    );
+    // The wider gaps no longer fit the width the plain text fits in.
+    assert_eq!(
+        draw("three short words", 0., WrapBehavior::Wrap(normal.width))
+            .1
+            .line_count,
+        1
+    );
+    assert!(
+        draw("three short words", 10., WrapBehavior::Wrap(normal.width))
+            .1
+            .line_count
+            > 1
+    );
+}
+
+#[test]
+fn test_justify_last_center() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let text = "Justified text on a poster keeps its edges straight and ends in the middle";
+    let mut background = DynamicImage::new_rgba8(300, 150);
+    let mut lines: Vec<LineMetrics> = Vec::new();
+    text_on_image_with_line_callback(
+        &mut background,
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::JustifyLastCenter,
+        VerticalAnchor::Top,
+        WrapBehavior::Wrap(280),
+        |metrics, _| lines.push(metrics),
+    );
+    save_output(&background, "test_justify_last_center.png").unwrap();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[..2]
+        .iter()
+        .all(|line| line.left == 10 && line.width == 280));
+    let last = &lines[2];
+    assert!(last.width < 280);
+    assert!((last.left + last.width as i32 / 2 - (10 + 140)).abs() <= 1);
+    // The ink of the last line is centered too.
+    let ink: Vec<u32> = background
+        .pixels()
+        .filter(|&(_, y, pixel)| {
+            pixel[3] > 0 && y as i32 >= last.top && y < (last.top as u32 + last.height)
+        })
+        .map(|(x, _, _)| x)
+        .collect();
+    let ink_center = (ink.iter().min().unwrap() + ink.iter().max().unwrap()) / 2;
+    assert!((ink_center as i32 - 150).abs() <= 3);
+    // A single line is the last line, so it is centered.
+    let single = measure_wrapped_text(
+        "Short",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::JustifyLastCenter,
+        VerticalAnchor::Top,
+        WrapBehavior::Wrap(280),
+    );
+    assert!((single.left + single.width as i32 / 2 - 150).abs() <= 1);
+    assert_eq!(
+        TextJustify::try_from(5).unwrap(),
+        TextJustify::JustifyLastCenter
+    );
+    assert_eq!(u8::from(TextJustify::JustifyLastCenter), 5);
+}
+
+#[test]
+fn test_animated_reveal_eases() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 24., y: 24. }, Rgba([0, 0, 0, 255]));
+    let base = DynamicImage::new_rgba8(260, 40);
+    let inked = |image: &DynamicImage| image.pixels().filter(|(_, _, pixel)| pixel[3] > 0).count();
+    let reveal = |ease| {
+        text_on_image_animated_reveal(
+            &base,
+            "Revealing text",
+            &font_bundle,
+            130,
+            5,
+            TextJustify::Center,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+            4,
+            ease,
+        )
+    };
+    let linear = reveal(EaseFunction::Linear);
+    assert_eq!(linear.len(), 4);
+    assert_eq!(inked(&linear[0]), 0);
+    let counts: Vec<usize> = linear.iter().map(inked).collect();
+    assert!(counts.windows(2).all(|pair| pair[0] < pair[1]));
+    // Characters stay where the whole string puts them.
+    let mut full = base.clone();
+    text_on_image(
+        &mut full,
+        "Revealing text",
+        &font_bundle,
+        130,
+        5,
+        TextJustify::Center,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    let leftmost_ink = |image: &DynamicImage| {
+        image
+            .pixels()
+            .filter(|(_, _, pixel)| pixel[3] > 0)
+            .map(|(x, _, _)| x)
+            .min()
+            .unwrap()
+    };
+    assert!(leftmost_ink(&linear[1]).abs_diff(leftmost_ink(&full)) <= 1);
+    // The last frame is the whole text, as is a lone frame. The two ways of drawing glyphs round
+    // coverage slightly differently.
+    let matches_full = |image: &DynamicImage| {
+        image
+            .pixels()
+            .zip(full.pixels())
+            .all(|((_, _, a), (_, _, b))| a[3].abs_diff(b[3]) <= 2)
+    };
+    assert!(matches_full(&linear[3]));
+    let single = text_on_image_animated_reveal(
+        &base,
+        "Revealing text",
+        &font_bundle,
+        130,
+        5,
+        TextJustify::Center,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        1,
+        EaseFunction::Linear,
+    );
+    assert_eq!(single.len(), 1);
+    assert!(matches_full(&single[0]));
+    save_output(&linear[2], "test_animated_reveal_eases.png").unwrap();
+    let eased_in = reveal(EaseFunction::EaseIn);
+    let eased_out = reveal(EaseFunction::EaseOut);
+    assert!(inked(&eased_in[2]) < counts[2]);
+    assert!(inked(&eased_out[1]) > counts[1]);
+    assert!((EaseFunction::CubicBezier(0., 0., 1., 1.).ease(0.3) - 0.3).abs() < 0.01);
+    assert!((EaseFunction::EaseInOut.ease(0.5) - 0.5).abs() < 0.001);
+}
+
+#[test]
+fn test_glyph_callback_draws_instead() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 24., y: 24. }, Rgba([0, 0, 0, 255]));
+    let mut background = DynamicImage::new_rgba8(200, 80);
+    let mut seen = Vec::new();
+    let measurement = text_on_image_with_glyph_callback(
+        &mut background,
+        "Hi\nthere",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        |image, info| {
+            if info.line_index == 1 {
+                if let Some(bounding_box) = info.glyph.pixel_bounding_box() {
+                    info.glyph.draw(|x, y, coverage| {
+                        if coverage > 0.5 {
+                            image.put_pixel(
+                                (bounding_box.min.x + x as i32) as u32,
+                                (bounding_box.min.y + y as i32) as u32,
+                                Rgba([255, 0, 0, 255]),
+                            );
+                        }
+                    });
+                }
+            }
+            seen.push((
+                info.char,
+                info.line_index,
+                info.char_index,
+                info.draw_x,
+                info.draw_y,
+            ));
+        },
+    );
+    save_output(&background, "test_glyph_callback_draws_instead.png").unwrap();
+    assert_eq!(measurement.line_count, 2);
+    let text: String = seen.iter().map(|&(c, ..)| c).collect();
+    assert_eq!(text, "Hithere");
+    assert_eq!(seen[2].1, 1);
+    assert_eq!(seen[2].2, 0);
+    assert_eq!(seen[2].3, 10);
+    assert!(seen[2].4 > seen[0].4);
+    // Nothing is drawn but what the callback draws.
+    assert!(background
+        .pixels()
+        .all(|(_, y, pixel)| pixel[3] == 0
+            || (pixel == Rgba([255, 0, 0, 255]) && y as i32 >= seen[0].4)));
+    assert!(background.pixels().any(|(_, _, pixel)| pixel[3] > 0));
+}
+
+#[test]
+fn test_conditional_font_picks_first_match() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let small = FontBundle::new(&font, Scale { x: 12., y: 12. }, Rgba([0, 0, 0, 255]));
+    let large = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([0, 0, 0, 255]));
+    let body = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let looks_like_code: TextPredicate = &|text| text.contains("()");
+    let is_short: TextPredicate = &|text| text.len() < 8;
+    let never: TextPredicate = &|_| false;
+    let bundles = [
+        (&small, looks_like_code),
+        (&large, is_short),
+        (&body, never),
+    ];
+    let mut background = DynamicImage::new_rgba8(400, 200);
+    let draw = |background: &mut DynamicImage, text: &str, y| {
+        text_on_image_conditional_font(
+            background,
+            text,
+            &bundles,
+            10,
+            y,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+        )
+    };
+    let height = |font_bundle| measure_text_height_for_lines(1, font_bundle);
+    assert_eq!(draw(&mut background, "main()", 10).height, height(&small));
+    assert_eq!(draw(&mut background, "Title", 30).height, height(&large));
+    assert_eq!(
+        draw(&mut background, "A longer sentence", 80).height,
+        height(&body)
+    );
+    save_output(&background, "test_conditional_font_picks_first_match.png").unwrap();
+}
+
+#[test]
+fn test_long_shadow_reaches_length() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([255, 255, 255, 255]));
+    let draw = |fade| {
+        let mut background = DynamicImage::new_rgba8(120, 80);
+        text_on_image_with_long_shadow(
+            &mut background,
+            "|",
+            &font_bundle,
+            20,
+            10,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+            LongShadowConfig {
+                angle_deg: 0.,
+                length: 20,
+                color: Rgba([0, 0, 255, 255]),
+                fade,
+            },
+        );
+        background
+    };
+    let solid = draw(false);
+    let faded = draw(true);
+    save_output(&faded, "test_long_shadow_reaches_length.png").unwrap();
+    let text_right = solid
+        .pixels()
+        .filter(|(_, _, pixel)| pixel == &Rgba([255, 255, 255, 255]))
+        .map(|(x, _, _)| x)
+        .max()
+        .unwrap();
+    let shadow_right = solid
+        .pixels()
+        .filter(|(_, _, pixel)| pixel[2] == 255 && pixel[0] == 0)
+        .map(|(x, _, _)| x)
+        .max()
+        .unwrap();
+    assert!((shadow_right - text_right).abs_diff(20) <= 1);
+    let row = (0..120)
+        .find(|&y| solid.get_pixel(text_right, y)[3] > 0)
+        .unwrap()
+        + 5;
+    let near = faded.get_pixel(text_right + 2, row)[3];
+    let far = faded.get_pixel(text_right + 18, row)[3];
+    assert!(near > far, "near {} far {}", near, far);
+}
+
+#[test]
+fn test_double_outline_layers() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([255, 255, 255, 255]));
+    let mut background = DynamicImage::new_rgba8(80, 70);
+    text_on_image_with_double_outline(
+        &mut background,
+        "|",
+        &font_bundle,
+        30,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        DoubleOutlineConfig {
+            inner: OutlineConfig {
+                width: 2,
+                color: Rgba([255, 0, 0, 255]),
+            },
+            outer: OutlineConfig {
+                width: 3,
+                color: Rgba([0, 0, 255, 255]),
+            },
+        },
+    );
+    save_output(&background, "test_double_outline_layers.png").unwrap();
+    let white = Rgba([255, 255, 255, 255]);
+    let y = 30;
+    let text_right = (0..80)
+        .filter(|&x| background.get_pixel(x, y) == white)
+        .max()
+        .unwrap();
+    // Moving right from the bar: fill, then red for 2 pixels, then blue for 3.
+    assert_eq!(
+        background.get_pixel(text_right + 2, y),
+        Rgba([255, 0, 0, 255])
+    );
+    assert_eq!(
+        background.get_pixel(text_right + 4, y),
+        Rgba([0, 0, 255, 255])
+    );
+    assert_eq!(background.get_pixel(text_right + 7, y)[3], 0);
+}
+
+#[test]
+fn test_text_transforms() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let draw_plain = |text: &str| {
+        let mut background = DynamicImage::new_rgba8(300, 40);
+        text_on_image(
+            &mut background,
+            text,
+            &font_bundle,
+            5,
+            5,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+        );
+        background
+    };
+    let original = String::from("semi-final straße");
+    let mut uppercase = DynamicImage::new_rgba8(300, 40);
+    text_on_image_uppercase(
+        &mut uppercase,
+        &original,
+        &font_bundle,
+        5,
+        5,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    assert_eq!(original, "semi-final straße");
+    assert!(uppercase == draw_plain("SEMI-FINAL STRASSE"));
+    #[cfg(feature = "capitalize")]
+    {
+        let mut capitalized = DynamicImage::new_rgba8(300, 40);
+        text_on_image_capitalize(
+            &mut capitalized,
+            "rock'n'roll semi-final",
+            &font_bundle,
+            5,
+            5,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+        );
+        save_output(&capitalized, "test_text_transforms.png").unwrap();
+        assert!(capitalized == draw_plain("Rock'n'roll Semi-Final"));
+    }
+}
+
+#[test]
+fn test_shadow_from_angle() {
+    let color = Rgba([0, 0, 0, 128]);
+    let right = ShadowConfig::from_angle(0., 5., color);
+    assert_eq!((right.offset_x, right.offset_y), (5, 0));
+    let below = ShadowConfig::from_angle(90., 5., color);
+    assert_eq!((below.offset_x, below.offset_y), (0, 5));
+    let up_left = ShadowConfig::from(AngleShadowConfig {
+        angle_deg: 225.,
+        distance_px: 4.,
+        color,
+    });
+    assert_eq!((up_left.offset_x, up_left.offset_y), (-3, -3));
+    assert_eq!(up_left.blur_radius, None);
+
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([255, 255, 255, 255]));
+    let draw_shadowed = |draw: &dyn Fn(&mut DynamicImage)| {
+        let mut background = DynamicImage::new_rgba8(80, 60);
+        draw(&mut background);
+        background
+    };
+    let by_angle = draw_shadowed(&|background| {
+        text_on_image_with_shadow_angle(
+            background,
+            "A",
+            &font_bundle,
+            20,
+            10,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+            AngleShadowConfig {
+                angle_deg: 45.,
+                distance_px: 5.,
+                color,
+            },
+        );
+    });
+    let by_offset = draw_shadowed(&|background| {
+        text_on_image_with_shadow_multiple(
+            background,
+            "A",
+            &font_bundle,
+            20,
+            10,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+            &[ShadowConfig {
+                offset_x: 4,
+                offset_y: 4,
+                color,
+                blur_radius: None,
+            }],
+        );
+    });
+    save_output(&by_angle, "test_shadow_from_angle.png").unwrap();
+    assert!(by_angle == by_offset);
+}
+
+#[test]
+fn test_border_styles() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let red = Rgba([255, 0, 0, 255]);
+    let draw = |style| {
+        let mut background = DynamicImage::new_rgba8(200, 60);
+        let measurement = text_on_image_with_border(
+            &mut background,
+            "Bordered",
+            &font_bundle,
+            100,
+            30,
+            TextJustify::Center,
+            VerticalAnchor::Center,
+            WrapBehavior::NoWrap,
+            6,
+            red,
+            2,
+            style,
+        );
+        (background, measurement)
+    };
+    let (solid, measurement) = draw(BorderStyle::Solid);
+    let top = (measurement.top - 8) as u32;
+    let left = (measurement.left - 8) as u32;
+    let right = left + measurement.width + 16 - 1;
+    let bottom = top + measurement.height + 16 - 1;
+    let top_row: Vec<Rgba<u8>> = (left..=right).map(|x| solid.get_pixel(x, top)).collect();
+    assert!(top_row.iter().all(|&pixel| pixel == red));
+    assert!((top..=bottom).all(|y| solid.get_pixel(right, y) == red));
+    assert_eq!(solid.get_pixel(left + 2, top + 2)[3], 0);
+    assert_eq!(solid.get_pixel(left - 1, top)[3], 0);
+
+    let (dashed, _) = draw(BorderStyle::Dashed {
+        dash_len: 5,
+        gap_len: 3,
+    });
+    save_output(&dashed, "test_border_styles.png").unwrap();
+    let dashes: Vec<bool> = (left..left + 8)
+        .map(|x| dashed.get_pixel(x, top) == red)
+        .collect();
+    assert_eq!(dashes, [true, true, true, true, true, false, false, false]);
+    let (dotted, _) = draw(BorderStyle::Dotted);
+    let dots: Vec<bool> = (left..left + 4)
+        .map(|x| dotted.get_pixel(x, bottom) == red)
+        .collect();
+    assert_eq!(dots, [true, true, false, false]);
+}
+
+#[test]
+fn test_measure_only_matches_drawing() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let text = "Layout without any pixels to draw on";
+    let layout = text_on_image_measure_only(
+        text,
+        &font_bundle,
+        100,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::Wrap(180),
+    );
+    let mut background = DynamicImage::new_rgba8(200, 100);
+    let measurement = text_on_image(
+        &mut background,
+        text,
+        &font_bundle,
+        100,
+        50,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::Wrap(180),
+    );
+    assert_eq!(layout.wrapped_text.len(), measurement.line_count);
+    assert_eq!(layout.lines.len(), measurement.line_count);
+    assert_eq!(layout.lines[1].text, layout.wrapped_text[1]);
+    assert_eq!(
+        (layout.total_width, layout.total_height),
+        (measurement.width, measurement.height)
+    );
+    let inked: Vec<(u32, u32)> = background
+        .pixels()
+        .filter(|(_, _, pixel)| pixel[3] > 0)
+        .map(|(x, y, _)| (x, y))
+        .collect();
+    let ink_left = inked.iter().map(|&(x, _)| x).min().unwrap() as i32;
+    let ink_bottom = inked.iter().map(|&(_, y)| y).max().unwrap() as i32;
+    assert_eq!(layout.bounding_box.left, ink_left);
+    assert_eq!(layout.bounding_box.bottom, ink_bottom + 1);
+}
+
+#[test]
+fn test_glyph_substitution() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([0, 0, 0, 255]));
+    let draw_plain = |text: &str| {
+        let mut background = DynamicImage::new_rgba8(100, 40);
+        text_on_image(
+            &mut background,
+            text,
+            &font_bundle,
+            5,
+            5,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+        );
+        background
+    };
+    let subs = GlyphSubstitutionMap {
+        substitutions: HashMap::from([('*', '#'), ('#', '@')]),
+    };
+    let mut substituted = DynamicImage::new_rgba8(100, 40);
+    text_on_image_with_substitution(
+        &mut substituted,
+        "a*#",
+        &font_bundle,
+        5,
+        5,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        &subs,
+    );
+    save_output(&substituted, "test_glyph_substitution.png").unwrap();
+    assert!(substituted == draw_plain("a#@"));
+    assert!(substituted != draw_plain("a*#"));
+}
+
+#[test]
+fn test_gradient_background() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([255, 255, 255, 255]));
+    let draw = |direction| {
+        let mut background = DynamicImage::new_rgba8(200, 60);
+        let measurement = text_on_image_with_gradient_background(
+            &mut background,
+            "Gradient",
+            &font_bundle,
+            100,
+            30,
+            TextJustify::Center,
+            VerticalAnchor::Center,
+            WrapBehavior::NoWrap,
+            GradientBackground {
+                start_color: Rgba([255, 0, 0, 255]),
+                end_color: Rgba([0, 0, 255, 255]),
+                direction,
+                padding: 5,
+            },
+        );
+        (background, measurement)
+    };
+    let (horizontal, measurement) = draw(GradientDirection::Horizontal);
+    save_output(&horizontal, "test_gradient_background.png").unwrap();
+    let left = (measurement.left - 5) as u32;
+    let right = left + measurement.width + 10 - 1;
+    let top = (measurement.top - 5) as u32;
+    assert_eq!(horizontal.get_pixel(left, top), Rgba([255, 0, 0, 255]));
+    assert_eq!(horizontal.get_pixel(right, top), Rgba([0, 0, 255, 255]));
+    let middle = horizontal.get_pixel((left + right) / 2, top);
+    assert!(middle[0].abs_diff(128) <= 2 && middle[2].abs_diff(128) <= 2);
+    assert_eq!(horizontal.get_pixel(left - 1, top)[3], 0);
+    let (vertical, _) = draw(GradientDirection::Vertical);
+    let bottom = top + measurement.height + 10 - 1;
+    assert_eq!(vertical.get_pixel(right, top), Rgba([255, 0, 0, 255]));
+    assert_eq!(vertical.get_pixel(left, bottom), Rgba([0, 0, 255, 255]));
+    let (diagonal, _) = draw(GradientDirection::Diagonal);
+    assert_eq!(diagonal.get_pixel(left, top), Rgba([255, 0, 0, 255]));
+    assert_eq!(diagonal.get_pixel(right, bottom), Rgba([0, 0, 255, 255]));
+}
+
+#[cfg(feature = "webp")]
+#[test]
+fn test_webp_bytes() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let base = DynamicImage::new_rgba8(120, 40);
+    let bytes = text_on_image_to_webp_bytes(
+        &base,
+        "WebP",
+        &font_bundle,
+        60,
+        20,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        80.,
+    )
+    .unwrap();
+    assert_eq!(&bytes[..4], b"RIFF");
+    assert_eq!(&bytes[8..12], b"WEBP");
+    let decoded = image::load_from_memory(&bytes).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (120, 40));
+    assert!(decoded.pixels().any(|(_, _, pixel)| pixel[3] > 128));
+    assert!(base.pixels().all(|(_, _, pixel)| pixel[3] == 0));
+}
+
+#[test]
+fn test_per_word_font() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let body = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let keyword = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([255, 0, 0, 255]));
+    let mut background = DynamicImage::new_rgba8(300, 40);
+    let measurement = text_on_image_with_per_word_font(
+        &mut background,
+        "let  x = fn",
+        &body,
+        |word| matches!(word, "let" | "fn").then_some(&keyword),
+        5,
+        5,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+    );
+    save_output(&background, "test_per_word_font.png").unwrap();
+    // Each run is measured on its own, so the widths may round differently.
+    assert!(
+        measurement
+            .width
+            .abs_diff(get_text_width(&body, "let  x = fn"))
+            <= 3
+    );
+    let inked_columns = |keep: &dyn Fn(Rgba<u8>) -> bool| -> Vec<u32> {
+        background
+            .pixels()
+            .filter(|&(_, _, pixel)| pixel[3] == 255 && keep(pixel))
+            .map(|(x, _, _)| x)
+            .collect()
+    };
+    let red = inked_columns(&|pixel| pixel[0] == 255);
+    let black = inked_columns(&|pixel| pixel[0] == 0);
+    let let_end = 5 + get_text_width(&body, "let");
+    let fn_start = 5 + get_text_width(&body, "let  x = ");
+    assert!(red.iter().all(|&x| x < let_end || x >= fn_start));
+    assert!(red.iter().any(|&x| x >= fn_start));
+    assert!(black.iter().all(|&x| (let_end..fn_start).contains(&x)));
+}
+
+#[test]
+fn test_clip_mask_shows_foreground_through_text() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([0, 0, 0, 255]));
+    let foreground = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(160, 60, |x, _| {
+        Rgba([(x * 255 / 159) as u8, 100, 0, 255])
+    }));
+    let background =
+        DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(200, 60, Rgba([0, 0, 80, 255])));
+    let composited = text_on_image_clip_mask(
+        &foreground,
+        &background,
+        "CUT OUT",
+        &font_bundle,
+        100,
+        30,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    save_output(
+        &composited,
+        "test_clip_mask_shows_foreground_through_text.png",
+    )
+    .unwrap();
+    assert_eq!((composited.width(), composited.height()), (200, 60));
+    let (mask, _) = render_text_to_mask(
+        200,
+        60,
+        "CUT OUT",
+        &font_bundle,
+        100,
+        30,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    for (x, y, coverage) in mask.enumerate_pixels() {
+        let pixel = composited.get_pixel(x, y);
+        if coverage[0] == 255 && x < 160 {
+            assert_eq!(pixel, foreground.get_pixel(x, y));
+        } else if coverage[0] == 0 || x >= 160 {
+            assert_eq!(pixel, Rgba([0, 0, 80, 255]));
+        }
+    }
+}
+
+#[test]
+fn test_zebra_rows() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let even = Rgba([200, 200, 200, 255]);
+    let odd = Rgba([240, 240, 240, 255]);
+    let mut background = DynamicImage::new_rgba8(220, 100);
+    let measurement = text_on_image_with_zebra(
+        &mut background,
+        "A long first line\nshort\nthird",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        ZebraConfig {
+            even_color: even,
+            odd_color: odd,
+            padding_x: 4,
+            padding_y: 3,
+        },
+    );
+    save_output(&background, "test_zebra_rows.png").unwrap();
+    let line_height = measurement.height / 3;
+    let right_edge = (measurement.left + measurement.width as i32 + 3) as u32;
+    let row_y = |line: u32| measurement.top as u32 + line * line_height + 1;
+    // Every row reaches as far right as the widest line, even the short ones.
+    assert_eq!(background.get_pixel(right_edge, row_y(0)), even);
+    assert_eq!(background.get_pixel(right_edge, row_y(1)), odd);
+    assert_eq!(background.get_pixel(right_edge, row_y(2)), even);
+    assert_eq!(background.get_pixel(right_edge + 1, row_y(1))[3], 0);
+    assert_eq!(background.get_pixel(6, measurement.top as u32 - 3), even);
+    assert_eq!(background.get_pixel(6, measurement.top as u32 - 4)[3], 0);
+    let bottom = (measurement.top as u32) + measurement.height;
+    assert_eq!(background.get_pixel(6, bottom + 2), even);
+    assert_eq!(background.get_pixel(6, bottom + 3)[3], 0);
+}
+
+#[test]
+fn test_combined_shadow() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([255, 255, 255, 255]));
+    let drop_shadow = ShadowConfig {
+        offset_x: 4,
+        offset_y: 4,
+        color: Rgba([0, 0, 0, 255]),
+        blur_radius: None,
+    };
+    let inner_shadow = InnerShadowConfig {
+        offset_x: 3,
+        offset_y: 3,
+        color: Rgba([0, 0, 255, 255]),
+        blur: 0.,
+    };
+    let draw = |config| {
+        let mut background = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            120,
+            60,
+            Rgba([200, 0, 0, 255]),
+        ));
+        text_on_image_with_combined_shadow(
+            &mut background,
+            "H",
+            &font_bundle,
+            10,
+            5,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+            config,
+        );
+        background
+    };
+    let count = |image: &DynamicImage, color: Rgba<u8>| {
+        image
+            .pixels()
+            .filter(|&(_, _, pixel)| pixel == color)
+            .count()
+    };
+    let plain = draw(CombinedShadowConfig::default());
+    let both = draw(CombinedShadowConfig {
+        drop_shadow: Some(drop_shadow),
+        inner_shadow: Some(inner_shadow),
+    });
+    let drop_only = draw(CombinedShadowConfig {
+        drop_shadow: Some(drop_shadow),
+        inner_shadow: None,
+    });
+    save_output(&both, "test_combined_shadow.png").unwrap();
+    let black = Rgba([0, 0, 0, 255]);
+    let blue = Rgba([0, 0, 255, 255]);
+    assert_eq!(count(&plain, black) + count(&plain, blue), 0);
+    assert!(count(&drop_only, black) > 0);
+    assert_eq!(count(&drop_only, blue), 0);
+    assert!(count(&both, blue) > 0);
+    assert_eq!(count(&both, black), count(&drop_only, black));
+}
+
+#[test]
+fn test_char_highlight() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let yellow = Rgba([255, 255, 0, 255]);
+    let mut background = DynamicImage::new_rgba8(200, 60);
+    let measurement = text_on_image_with_char_highlight(
+        &mut background,
+        "cursor\nat 3",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        10,
+        yellow,
+    )
+    .unwrap();
+    save_output(&background, "test_char_highlight.png").unwrap();
+    let positions = get_char_positions(&font_bundle, "at 3");
+    let line_height = measurement.height / 2;
+    let highlighted: Vec<(u32, u32)> = background
+        .pixels()
+        .filter(|&(_, _, pixel)| pixel == yellow)
+        .map(|(x, y, _)| (x, y))
+        .collect();
+    let left = highlighted.iter().map(|&(x, _)| x).min().unwrap();
+    let right = highlighted.iter().map(|&(x, _)| x).max().unwrap();
+    let top = highlighted.iter().map(|&(_, y)| y).min().unwrap();
+    assert_eq!(left, 10 + positions[3]);
+    assert_eq!(right, 10 + positions[4] - 1);
+    assert_eq!(top, 10 + line_height);
+
+    let mut untouched = DynamicImage::new_rgba8(200, 60);
+    let out_of_range = text_on_image_with_char_highlight(
+        &mut untouched,
+        "cursor\nat 3",
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        11,
+        yellow,
+    );
+    assert_eq!(out_of_range, None);
+    assert!(untouched.pixels().all(|(_, _, pixel)| pixel[3] == 0));
+}
+
+#[test]
+fn test_char_highlight_indexes_source_text() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let yellow = Rgba([255, 255, 0, 255]);
+    // The leftmost and rightmost highlighted columns and the top row, if anything was highlighted.
+    let highlight = |text: &str, horizontal_justify, wrap_behavior, char_index| {
+        let mut background = DynamicImage::new_rgba8(300, 100);
+        let measurement = text_on_image_with_char_highlight(
+            &mut background,
+            text,
+            &font_bundle,
+            10,
+            10,
+            horizontal_justify,
+            VerticalAnchor::Top,
+            wrap_behavior,
+            char_index,
+            yellow,
+        );
+        let highlighted: Vec<(u32, u32)> = background
+            .pixels()
+            .filter(|&(_, _, pixel)| pixel == yellow)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        measurement.map(|_| {
+            (
+                highlighted.iter().map(|&(x, _)| x).min().unwrap(),
+                highlighted.iter().map(|&(x, _)| x).max().unwrap(),
+                highlighted.iter().map(|&(_, y)| y).min().unwrap(),
+            )
+        })
+    };
+    let positions = get_char_positions(&font_bundle, "ab");
+    let line_height = get_text_height(&font_bundle) as u32;
+
+    // Leading whitespace is trimmed from the line but still counted.
+    let leading = highlight("  ab", TextJustify::Left, WrapBehavior::NoWrap, 2);
+    assert_eq!(leading, Some((10, 10 + positions[1] - 1, 10)));
+    assert_eq!(
+        highlight("  ab", TextJustify::Left, WrapBehavior::NoWrap, 0),
+        None
+    );
+
+    // Newlines are counted too.
+    let after_newline = highlight("Hello\nWorld", TextJustify::Left, WrapBehavior::NoWrap, 10);
+    let world = get_char_positions(&font_bundle, "World");
+    assert_eq!(
+        after_newline,
+        Some((10 + world[4], 10 + world[5] - 1, 10 + line_height))
+    );
+    assert_eq!(
+        highlight("Hello\nWorld", TextJustify::Left, WrapBehavior::NoWrap, 11),
+        None
+    );
+
+    // Hyphens added by wrapping are not counted.
+    let long_word = "abcdefghijklmnopqrstuvwxyz";
+    let hyphenated = wrap_text_to_lines(long_word, &font_bundle, &WrapBehavior::Wrap(100));
+    assert!(hyphenated[0].ends_with('-'));
+    let last = highlight(long_word, TextJustify::Left, WrapBehavior::Wrap(100), 25);
+    let last_line = hyphenated.last().unwrap();
+    let last_positions = get_char_positions(&font_bundle, last_line);
+    let last_offset = last_line.chars().count() - 1;
+    assert_eq!(
+        last,
+        Some((
+            10 + last_positions[last_offset],
+            10 + last_positions[last_offset + 1] - 1,
+            10 + line_height * (hyphenated.len() as u32 - 1)
+        ))
+    );
+    assert_eq!(
+        highlight(long_word, TextJustify::Left, WrapBehavior::Wrap(100), 26),
+        None
+    );
+
+    // The box follows the words of stretched justified lines.
+    let text = "one two three four five six seven";
+    let wrap_behavior = WrapBehavior::Wrap(200);
+    let lines = wrap_text_to_lines(text, &font_bundle, &wrap_behavior);
+    let char_index = lines[0].chars().count() - 1;
+    let mut glyph_span = None;
+    text_on_image_with_glyph_callback(
+        &mut DynamicImage::new_rgba8(300, 100),
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::JustifyLastLeft,
+        VerticalAnchor::Top,
+        wrap_behavior,
+        |_, glyph| {
+            if glyph.line_index == 0 && glyph.char_index == char_index {
+                let advance = glyph.glyph.unpositioned().h_metrics().advance_width as u32;
+                glyph_span = Some((glyph.draw_x as u32, glyph.draw_x as u32 + advance - 1));
+            }
+        },
+    );
+    let (glyph_left, glyph_right) = glyph_span.unwrap();
+    assert!(glyph_left > 10 + get_char_positions(&font_bundle, &lines[0])[char_index]);
+    assert_eq!(
+        highlight(
+            text,
+            TextJustify::JustifyLastLeft,
+            wrap_behavior,
+            char_index
+        ),
+        Some((glyph_left, glyph_right, 10))
+    );
+}
+
+#[test]
+fn test_no_ligature_substitution() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let mut background = DynamicImage::new_rgba8(100, 30);
+    let mut glyphs = Vec::new();
+    text_on_image_with_glyph_callback(
+        &mut background,
+        "office",
+        &font_bundle,
+        0,
+        0,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        WrapBehavior::NoWrap,
+        |_, info| glyphs.push((info.char, info.glyph.id(), info.draw_x)),
+    );
+    let positions = get_char_positions(&font_bundle, "office");
+    assert_eq!(glyphs.len(), 6);
+    for (index, &(c, id, x)) in glyphs.iter().enumerate() {
+        assert_eq!(id, font.glyph(c).id());
+        assert!((x - positions[index] as i32).abs() <= 1);
+    }
+}
+
+#[test]
+fn test_tile_background_patterns() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 20., y: 20. }, Rgba([0, 0, 0, 255]));
+    let a = Rgba([255, 255, 255, 255]);
+    let b = Rgba([255, 0, 255, 255]);
+    let draw = |pattern| {
+        let mut background = DynamicImage::new_rgba8(200, 60);
+        let measurement = text_on_image_with_tile_background(
+            &mut background,
+            "Tiles",
+            &font_bundle,
+            100,
+            30,
+            TextJustify::Center,
+            VerticalAnchor::Center,
+            WrapBehavior::NoWrap,
+            8,
+            pattern,
+        );
+        (background, measurement)
+    };
+    let (checkerboard, measurement) = draw(TilePattern::Checkerboard {
+        size: 4,
+        color_a: a,
+        color_b: b,
+    });
+    save_output(&checkerboard, "test_tile_background_patterns.png").unwrap();
+    let (left, top) = ((measurement.left - 8) as u32, (measurement.top - 8) as u32);
+    assert_eq!(checkerboard.get_pixel(left, top), a);
+    assert_eq!(checkerboard.get_pixel(left + 3, top + 3), a);
+    assert_eq!(checkerboard.get_pixel(left + 4, top), b);
+    assert_eq!(checkerboard.get_pixel(left + 4, top + 4), a);
+    assert_eq!(checkerboard.get_pixel(left - 1, top)[3], 0);
+    let (vertical, _) = draw(TilePattern::Stripes {
+        width: 3,
+        angle_deg: 90.,
+        color_a: a,
+        color_b: b,
+    });
+    let column = |x| vertical.get_pixel(x, top + 2);
+    assert!((top..top + 6).all(|y| vertical.get_pixel(left + 1, y) == column(left + 1)));
+    assert!(column(left) == column(left + 2) && column(left + 2) != column(left + 3));
+    assert!(column(left + 3) == column(left + 5) && column(left + 5) != column(left + 6));
+    let (horizontal, _) = draw(TilePattern::Stripes {
+        width: 3,
+        angle_deg: 0.,
+        color_a: a,
+        color_b: b,
+    });
+    assert_eq!(horizontal.get_pixel(left + 5, top), a);
+    assert_eq!(horizontal.get_pixel(left + 5, top + 3), b);
+}
+
+#[test]
+fn test_paragraphs_indent_and_spacing() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 16., y: 16. }, Rgba([0, 0, 0, 255]));
+    let text = "First paragraph wraps onto a second line.\n\nSecond one\ncontinues here.";
+    let mut ink_lefts = Vec::new();
+    let line_height = measure_text_height_for_lines(1, &font_bundle);
+    let mut background = DynamicImage::new_rgba8(260, 160);
+    let measurement = text_on_image_with_paragraphs(
+        &mut background,
+        text,
+        &font_bundle,
+        10,
+        10,
+        TextJustify::Left,
+        VerticalAnchor::Top,
+        240,
+        ParagraphConfig {
+            first_line_indent_px: 30,
+            paragraph_spacing_px: 12,
+        },
+    );
+    save_output(&background, "test_paragraphs_indent_and_spacing.png").unwrap();
+    assert_eq!(measurement.line_count, 4);
+    assert_eq!(measurement.height, 4 * line_height + 12);
+    for line in 0..4u32 {
+        let gap = if line >= 2 { 12 } else { 0 };
+        let top = 10 + line * line_height + gap;
+        let ink_left = background
+            .pixels()
+            .filter(|&(_, y, pixel)| pixel[3] > 0 && (top..top + line_height).contains(&y))
+            .map(|(x, _, _)| x)
+            .min()
+            .unwrap();
+        ink_lefts.push(ink_left);
+    }
+    assert!(ink_lefts[0] >= 40 && ink_lefts[0] < 44, "{:?}", ink_lefts);
+    assert!(ink_lefts[1] < 14, "{:?}", ink_lefts);
+    assert!(ink_lefts[2] >= 40 && ink_lefts[2] < 44, "{:?}", ink_lefts);
+    assert!(ink_lefts[3] < 14, "{:?}", ink_lefts);
+    let gap_row = 10 + 2 * line_height + 6;
+    assert!((0..260).all(|x| background.get_pixel(x, gap_row)[3] == 0));
+}
+
+#[test]
+fn test_watermark_grid_covers_image() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 16., y: 16. }, Rgba([0, 0, 0, 255]));
+    let config = WatermarkGridConfig {
+        text: "SAMPLE".to_string(),
+        font_bundle,
+        spacing_x: 20,
+        spacing_y: 20,
+        angle_deg: 30.,
+        opacity: 128,
+    };
+    let mut background = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+        300,
+        200,
+        Rgba([255, 255, 255, 255]),
+    ));
+    text_on_image_watermark_grid(&mut background, &config);
+    save_output(&background, "test_watermark_grid_covers_image.png").unwrap();
+    // Every corner region gets some of the watermark.
+    for (corner_x, corner_y) in [(0, 0), (240, 0), (0, 140), (240, 140)] {
+        let marked = (corner_x..corner_x + 60)
+            .flat_map(|x| (corner_y..corner_y + 60).map(move |y| (x, y)))
+            .any(|(x, y)| background.get_pixel(x, y)[0] < 255);
+        assert!(marked, "corner at ({}, {})", corner_x, corner_y);
+    }
+    // Half opacity never gets darker than half gray.
+    assert!(background.pixels().all(|(_, _, pixel)| pixel[0] >= 126));
+    assert!(background.pixels().any(|(_, _, pixel)| pixel[0] < 140));
+}
+
+#[test]
+fn test_image_derived_shadow_keeps_hue() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([255, 255, 255, 255]));
+    // Green on the left half and blue on the right.
+    let mut background = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(160, 60, |x, _| {
+        if x < 80 {
+            Rgba([0, 200, 0, 255])
+        } else {
+            Rgba([0, 0, 200, 255])
+        }
+    }));
+    text_on_image_image_derived_shadow(
+        &mut background,
+        "|||||||",
+        &font_bundle,
+        80,
+        30,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        0.,
+        0.5,
+        (3, 3),
+    );
+    save_output(&background, "test_image_derived_shadow_keeps_hue.png").unwrap();
+    let shaded_green = background
+        .pixels()
+        .any(|(x, _, pixel)| x < 70 && pixel == Rgba([0, 100, 0, 255]));
+    let shaded_blue = background
+        .pixels()
+        .any(|(x, _, pixel)| x > 90 && pixel == Rgba([0, 0, 100, 255]));
+    assert!(shaded_green && shaded_blue);
+    // Away from the white text, the shadow only darkens the color beneath it.
+    assert!(background
+        .pixels()
+        .filter(|&(_, _, pixel)| pixel[0] == 0)
+        .all(|(_, _, pixel)| pixel[1] == 0 || pixel[2] == 0));
+}
+
+#[test]
+fn test_auto_contrast_picks_readable_color() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    // Dark on the left half and light on the right.
+    let mut background = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(200, 40, |x, _| {
+        if x < 100 {
+            Rgba([20, 30, 60, 255])
+        } else {
+            Rgba([250, 240, 200, 255])
+        }
+    }));
+    for x in [50, 150] {
+        text_on_image_auto_contrast(
+            &mut background,
+            "Text",
+            &font,
+            Scale { x: 24., y: 24. },
+            x,
+            20,
+            TextJustify::Center,
+            VerticalAnchor::Center,
+            WrapBehavior::NoWrap,
+        );
+    }
+    save_output(&background, "test_auto_contrast_picks_readable_color.png").unwrap();
+    let has = |color: Rgba<u8>, left_half: bool| {
+        background
+            .pixels()
+            .any(|(x, _, pixel)| (x < 100) == left_half && pixel == color)
+    };
+    assert!(has(Rgba([255, 255, 255, 255]), true));
+    assert!(!has(Rgba([0, 0, 0, 255]), true));
+    assert!(has(Rgba([0, 0, 0, 255]), false));
+    assert!(!has(Rgba([255, 255, 255, 255]), false));
+}
+
+#[test]
+fn test_spread_shadow_grows() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([255, 255, 255, 255]));
+    let shadow_columns = |spread_px| {
+        let mut background = DynamicImage::new_rgba8(100, 60);
+        text_on_image_with_spread_shadow(
+            &mut background,
+            "|",
+            &font_bundle,
+            40,
+            10,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+            SpreadShadowConfig {
+                offset_x: 2,
+                offset_y: 2,
+                spread_px,
+                blur_radius: 0.,
+                color: Rgba([0, 0, 0, 255]),
+            },
+        );
+        let columns: Vec<u32> = background
+            .pixels()
+            .filter(|&(_, _, pixel)| pixel == Rgba([0, 0, 0, 255]))
+            .map(|(x, _, _)| x)
+            .collect();
+        (
+            background,
+            *columns.iter().min().unwrap(),
+            *columns.iter().max().unwrap(),
+        )
+    };
+    let (_, narrow_left, narrow_right) = shadow_columns(0);
+    let (spread, wide_left, wide_right) = shadow_columns(4);
+    save_output(&spread, "test_spread_shadow_grows.png").unwrap();
+    assert!(narrow_left - wide_left >= 4);
+    assert!(wide_right - narrow_right >= 4);
+}
+
+#[test]
+fn test_outline_and_background_layering() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 30., y: 30. }, Rgba([255, 255, 255, 255]));
+    let red = Rgba([255, 0, 0, 255]);
+    let gray = Rgba([60, 60, 60, 255]);
+    let mut background = DynamicImage::new_rgba8(200, 80);
+    let text_area = measure_wrapped_text(
+        "Box",
+        &font_bundle,
+        100,
+        40,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+    );
+    let measurement = text_on_image_with_outline_and_background(
+        &mut background,
+        "Box",
+        &font_bundle,
+        100,
+        40,
+        TextJustify::Center,
+        VerticalAnchor::Center,
+        WrapBehavior::NoWrap,
+        OutlineConfig {
+            width: 3,
+            color: red,
+        },
+        BackgroundConfig {
+            color: gray,
+            padding: 4,
+        },
+    );
+    save_output(&background, "test_outline_and_background_layering.png").unwrap();
+    assert_eq!(measurement.left, text_area.left - 7);
+    assert_eq!(measurement.top, text_area.top - 7);
+    assert_eq!(measurement.width, text_area.width + 14);
+    assert_eq!(measurement.height, text_area.height + 14);
+    assert_eq!(measurement.line_count, 1);
+    let (left, top) = (measurement.left as u32, measurement.top as u32);
+    let (right, bottom) = (left + measurement.width - 1, top + measurement.height - 1);
+    for (x, y) in [(left, top), (right, top), (left, bottom), (right, bottom)] {
+        assert_eq!(background.get_pixel(x, y), gray);
+    }
+    assert_eq!(background.get_pixel(left - 1, top)[3], 0);
+    // The outline is drawn over the background, not hidden by it.
+    assert!(background.pixels().any(|(_, _, pixel)| pixel == red));
+}
+
+#[test]
+fn test_auto_shadow_darkens_text_color() {
+    let font = Vec::from(FONT);
+    let font = Font::try_from_vec(font).unwrap();
+    let font_bundle = FontBundle::new(&font, Scale { x: 40., y: 40. }, Rgba([200, 100, 40, 255]));
+    let shadow_color = |darkness: f32| {
+        let mut background = DynamicImage::new_rgba8(200, 80);
+        text_on_image_with_auto_shadow(
+            &mut background,
+            "I",
+            &font_bundle,
+            20,
+            10,
+            TextJustify::Left,
+            VerticalAnchor::Top,
+            WrapBehavior::NoWrap,
+            (40, 0),
+            darkness,
+        );
+        save_output(
+            &background,
+            &format!("test_auto_shadow_darkens_text_color_{}.png", darkness),
+        )
+        .unwrap();
+        // The opaque pixels past the text are all shadow.
+        background
+            .pixels()
+            .find(|&(x, _, pixel)| x >= 50 && pixel[3] == 255)
+            .unwrap()
+            .2
+    };
+    assert_eq!(shadow_color(0.), Rgba([200, 100, 40, 255]));
+    assert_eq!(shadow_color(0.5), Rgba([100, 50, 20, 255]));
+    assert_eq!(shadow_color(1.), Rgba([0, 0, 0, 255]));
+    assert_eq!(shadow_color(3.), Rgba([0, 0, 0, 255]));
+}